@@ -0,0 +1,80 @@
+/// Move search used to play against the server and to analyse games
+mod engine_options;
+mod evaluation;
+mod perft;
+mod position;
+mod search;
+mod search_limits;
+mod time_manager;
+mod transposition_table;
+
+pub use engine_options::EngineOptions;
+pub use evaluation::{evaluate, evaluate_with_weights, EvaluationWeights};
+pub use perft::{divide, perft};
+pub use position::Position;
+pub use search::SearchResult;
+#[cfg(test)]
+pub use search::WIN_SCORE;
+pub use search_limits::SearchLimits;
+pub use time_manager::StopSignal;
+use time_manager::TimeManager;
+use transposition_table::TranspositionTable;
+
+/// Size of the transposition table when none is given, in megabytes
+const DEFAULT_HASH_MB: usize = 16;
+/// Largest transposition table an engine can be given, in megabytes
+pub const MAX_HASH_MB: usize = 256;
+/// Most threads a single search can use
+pub const MAX_THREADS: usize = 16;
+
+/// Searches positions, keeping its transposition table between searches
+#[derive(Debug)]
+pub struct Engine {
+    pub options: EngineOptions,
+    hash_mb: usize,                    // size of the transposition table in megabytes
+    table: Option<TranspositionTable>, // allocated by the first search
+    stop_signal: StopSignal,
+}
+
+impl Engine {
+    #[cfg(test)]
+    pub fn init() -> Engine {
+        return Engine::init_with_stop_signal(StopSignal::init());
+    }
+
+    /// Create an engine whose searches end early when `stop_signal` is stopped
+    pub fn init_with_stop_signal(stop_signal: StopSignal) -> Engine {
+        return Engine {
+            options: EngineOptions::init(),
+            hash_mb: DEFAULT_HASH_MB,
+            table: None,
+            stop_signal,
+        };
+    }
+
+    /// Replace the transposition table with an empty one of `size_mb` megabytes, allocated by
+    /// the next search
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.hash_mb = size_mb;
+        self.table = None;
+    }
+
+    /// Search for the best move in a position within the given limits. The search can be
+    /// ended early from another thread through the engine's stop signal, which has to be
+    /// reset before the next search. A stop sent before the search starts ends it as soon as
+    /// it has a move.
+    ///
+    /// **Returns** - None if the side to move has no legal moves
+    pub fn best_move(
+        &mut self,
+        position: &Position,
+        limits: &SearchLimits,
+    ) -> Option<SearchResult> {
+        let time_manager = TimeManager::init(limits, self.stop_signal.clone());
+        let hash_mb = self.hash_mb;
+        let table = self
+            .table
+            .get_or_insert_with(|| TranspositionTable::init(hash_mb));
+        return search::best_move(position, limits, &self.options, table, &time_manager);
+    }
+}
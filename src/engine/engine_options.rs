@@ -0,0 +1,22 @@
+use super::EvaluationWeights;
+
+/// Plies of captures resolved past the search depth when none is given
+const DEFAULT_QUIESCENCE_DEPTH: u32 = 24;
+
+/// Settings used by every search an engine runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineOptions {
+    pub weights: EvaluationWeights,
+    pub quiescence_depth: u32, // most capture plies searched past the depth limit, 0 disables
+    pub threads: usize,        // threads searching in parallel, sharing the transposition table
+}
+
+impl EngineOptions {
+    pub fn init() -> EngineOptions {
+        return EngineOptions {
+            weights: EvaluationWeights::init(),
+            quiescence_depth: DEFAULT_QUIESCENCE_DEPTH,
+            threads: 1,
+        };
+    }
+}
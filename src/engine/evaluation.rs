@@ -0,0 +1,75 @@
+use crate::game::{CheckersBoard, Side};
+
+/// Squares in the middle of the board, rows 3 and 4 between columns 2 and 5
+const CENTER_ROWS: [usize; 2] = [3, 4];
+const CENTER_COLS: [usize; 4] = [2, 3, 4, 5];
+
+/// Weight of each term of the evaluation, every term is scored per piece or per move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvaluationWeights {
+    pub man: i32,
+    pub king: i32,
+    pub advancement: i32, // per row a man has moved away from its own back rank, the tempo count
+    pub back_rank: i32,   // per man still guarding its own back rank
+    pub center: i32,      // per piece on a center square
+    pub mobility: i32,    // per legal move
+    pub to_move: i32,     // bonus for being the side to move
+}
+
+impl EvaluationWeights {
+    pub fn init() -> EvaluationWeights {
+        return EvaluationWeights {
+            man: 100,
+            king: 160,
+            advancement: 2,
+            back_rank: 8,
+            center: 6,
+            mobility: 2,
+            to_move: 5,
+        };
+    }
+}
+
+/// Score a board from the point of view of `side` using the default weights, the weights every
+/// engine searches with
+pub fn evaluate(board: &CheckersBoard, side: Side) -> i32 {
+    return evaluate_with_weights(board, side, &EvaluationWeights::init());
+}
+
+/// Score a board from the point of view of `side`, which is taken to be the side to move.
+///
+/// **Returns** - positive scores when `side` is better off, negative when its opponent is
+pub fn evaluate_with_weights(
+    board: &CheckersBoard,
+    side: Side,
+    weights: &EvaluationWeights,
+) -> i32 {
+    return weights.to_move + score_side(board, side, weights)
+        - score_side(board, side.opponent(), weights);
+}
+
+/// Sum of every term for a single side's pieces
+fn score_side(board: &CheckersBoard, side: Side, weights: &EvaluationWeights) -> i32 {
+    let back_row = match side {
+        Side::Dark => 0,
+        Side::Light => 7,
+    };
+    let mut score = 0;
+    for piece in board.get_player_pieces(side) {
+        let (row, col) = piece.loc;
+        if piece.kinged {
+            score += weights.king;
+        } else {
+            score += weights.man;
+            score += weights.advancement * row.abs_diff(back_row) as i32;
+            if row == back_row {
+                score += weights.back_rank;
+            }
+        }
+        if CENTER_ROWS.contains(&row) && CENTER_COLS.contains(&col) {
+            score += weights.center;
+        }
+    }
+    score += weights.mobility * board.find_legal_moves(side).len() as i32;
+    return score;
+}
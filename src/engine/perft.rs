@@ -0,0 +1,33 @@
+use super::Position;
+use crate::game::Move;
+
+/// Count the leaf nodes of the move tree `depth` plies deep, used to check the move generator
+/// against known numbers. Every complete move counts once, however many jumps it makes.
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    return moves
+        .iter()
+        .map(|mv| perft(&position.make_move(mv), depth - 1))
+        .sum();
+}
+
+/// Split the perft count by the first move, to narrow down which move a wrong count comes from
+pub fn divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
+    return position
+        .legal_moves()
+        .into_iter()
+        .map(|mv| {
+            let count = perft(&position.make_move(&mv), depth - 1);
+            (mv, count)
+        })
+        .collect();
+}
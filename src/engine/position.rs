@@ -0,0 +1,30 @@
+use crate::game::{CheckersBoard, Move, Side};
+
+/// A board along with the side to move, everything the engine needs to search from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub board: CheckersBoard,
+    pub turn: Side,
+}
+
+impl Position {
+    pub fn init(board: CheckersBoard, turn: Side) -> Position {
+        return Position { board, turn };
+    }
+
+    /// Zobrist hash of the board and side to move
+    pub fn get_hash(&self) -> u64 {
+        return self.board.get_position_hash(self.turn);
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        return self.board.find_legal_moves(self.turn);
+    }
+
+    /// Position reached by playing a legal move, with the opponent to move
+    pub fn make_move(&self, mv: &Move) -> Position {
+        let mut board = self.board;
+        board.apply_move(mv);
+        return Position::init(board, self.turn.opponent());
+    }
+}
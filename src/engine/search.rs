@@ -0,0 +1,337 @@
+use super::transposition_table::{Bound, TranspositionTable};
+use super::{
+    evaluate_with_weights, EngineOptions, Position, SearchLimits, StopSignal, TimeManager,
+};
+use crate::game::Move;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+/// Score of a won position, reduced by the number of plies it takes to win
+pub const WIN_SCORE: i32 = 100_000;
+/// Bound outside of every possible score
+const INFINITY: i32 = WIN_SCORE + 1;
+/// Deepest ply the search will ever reach
+const MAX_PLY: u32 = 128;
+/// Nodes searched between checks of the time manager, and between adding a thread's nodes to
+/// the count shared by every thread
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Outcome of a search, scores are from the point of view of the side to move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: i32,
+    pub depth: u32,    // depth of the last completed iteration
+    pub pv: Vec<Move>, // principal variation, starting with `best_move`
+    pub nodes: u64,    // nodes searched, including any iteration cut short
+}
+
+/// Search for the best move in a position with iterative deepening, each iteration running a
+/// negamax alpha-beta search one ply deeper than the last. Moves stored in the transposition
+/// table by earlier iterations are tried first. When the time manager stops the search part way
+/// through an iteration the result of the last completed iteration is used, the first iteration
+/// always runs to completion so there is a move to play.
+///
+/// With more than one thread the extra threads run the same search Lazy SMP style, sharing the
+/// transposition table with the main thread. Half of them search one ply deeper so they fill
+/// in the table ahead of the main thread. The main thread's result is the one returned. The
+/// node limit is checked against the nodes searched by every thread together.
+///
+/// **Returns** - None if the side to move has no legal moves
+pub fn best_move(
+    position: &Position,
+    limits: &SearchLimits,
+    options: &EngineOptions,
+    table: &mut TranspositionTable,
+    time_manager: &TimeManager,
+) -> Option<SearchResult> {
+    table.new_search();
+    let table = &*table;
+    let main_done = StopSignal::init();
+    let shared_nodes = AtomicU64::new(0);
+    return thread::scope(|scope| {
+        let helpers: Vec<_> = (1..options.threads.max(1))
+            .map(|id| {
+                let main_done = &main_done;
+                let shared_nodes = &shared_nodes;
+                scope.spawn(move || {
+                    let mut search =
+                        Search::init(options, table, time_manager, main_done, shared_nodes);
+                    search.abortable = true;
+                    search.iterative_deepening(position, limits, 1 + id as u32 % 2);
+                    search.nodes
+                })
+            })
+            .collect();
+
+        let mut search = Search::init(options, table, time_manager, &main_done, &shared_nodes);
+        let mut result = search.iterative_deepening(position, limits, 1);
+        main_done.stop();
+        let helper_nodes: u64 = helpers
+            .into_iter()
+            .map(|helper| helper.join().unwrap_or(0))
+            .sum();
+        if let Some(result) = result.as_mut() {
+            result.nodes = search.nodes + helper_nodes;
+        }
+        result
+    });
+}
+
+/// Check if a score means one side has a forced win
+fn is_decisive(score: i32) -> bool {
+    return score.abs() >= WIN_SCORE - MAX_PLY as i32;
+}
+
+/// Win and loss scores count plies from the root, the table stores them counting from the
+/// position itself so they stay correct when the position is reached at a different ply
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    match score {
+        s if is_decisive(s) && s > 0 => s + ply as i32,
+        s if is_decisive(s) => s - ply as i32,
+        s => s,
+    }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    match score {
+        s if is_decisive(s) && s > 0 => s - ply as i32,
+        s if is_decisive(s) => s + ply as i32,
+        s => s,
+    }
+}
+
+struct Search<'a> {
+    nodes: u64,
+    options: &'a EngineOptions,
+    table: &'a TranspositionTable,
+    time_manager: &'a TimeManager,
+    main_done: &'a StopSignal, // stops helper threads once the main thread has finished
+    shared_nodes: &'a AtomicU64, // nodes searched by every thread, added every `CHECK_INTERVAL`
+    path: Vec<u64>,            // hashes of the positions leading to the one being searched
+    abortable: bool, // set once an iteration has completed and there is a move to fall back on
+    aborted: bool,
+}
+
+impl<'a> Search<'a> {
+    fn init(
+        options: &'a EngineOptions,
+        table: &'a TranspositionTable,
+        time_manager: &'a TimeManager,
+        main_done: &'a StopSignal,
+        shared_nodes: &'a AtomicU64,
+    ) -> Search<'a> {
+        return Search {
+            nodes: 0,
+            options,
+            table,
+            time_manager,
+            main_done,
+            shared_nodes,
+            path: vec![],
+            abortable: false,
+            aborted: false,
+        };
+    }
+
+    /// Search one ply deeper each iteration, starting at `first_depth`, until a limit is reached
+    fn iterative_deepening(
+        &mut self,
+        position: &Position,
+        limits: &SearchLimits,
+        first_depth: u32,
+    ) -> Option<SearchResult> {
+        let mut result: Option<SearchResult> = None;
+        for depth in first_depth..=limits.depth.unwrap_or(MAX_PLY).clamp(1, MAX_PLY) {
+            if result.is_some() && !self.time_manager.can_start_iteration(self.total_nodes()) {
+                break;
+            }
+            let mut pv = vec![];
+            let score = self.negamax(position, depth, 0, -INFINITY, INFINITY, &mut pv);
+            if self.aborted {
+                break;
+            }
+            let best_move = pv.first()?.clone();
+            result = Some(SearchResult {
+                best_move,
+                score,
+                depth,
+                pv,
+                nodes: self.nodes,
+            });
+            self.abortable = true;
+            if is_decisive(score) {
+                // the shortest forced win or loss has been found, deeper won't change it
+                break;
+            }
+        }
+        return result;
+    }
+
+    /// Count a node, checking in with the time manager every so often.
+    ///
+    /// **Returns** - true if the search has been stopped and should unwind
+    fn visit_node(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.shared_nodes
+                .fetch_add(CHECK_INTERVAL, Ordering::Relaxed);
+            if self.abortable
+                && (self.time_manager.should_stop(self.total_nodes())
+                    || self.main_done.is_stopped())
+            {
+                self.aborted = true;
+            }
+        }
+        return self.aborted;
+    }
+
+    /// Nodes searched so far by every thread, as far as this thread knows
+    fn total_nodes(&self) -> u64 {
+        return self.shared_nodes.load(Ordering::Relaxed) + self.nodes % CHECK_INTERVAL;
+    }
+
+    /// Negamax alpha-beta search, filling `pv` with the best line found from this position
+    fn negamax(
+        &mut self,
+        position: &Position,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        if self.visit_node() {
+            return 0;
+        }
+        let hash = position.get_hash();
+        if self.path.contains(&hash) {
+            // repeating a position along the line is treated as a draw
+            return 0;
+        }
+        let moves = position.legal_moves();
+        if moves.is_empty() {
+            // the side to move is blocked or out of pieces
+            return -(WIN_SCORE - ply as i32);
+        }
+        if depth == 0 {
+            return self.quiescence(position, ply, self.options.quiescence_depth, alpha, beta);
+        }
+
+        let mut order: Vec<usize> = (0..moves.len()).collect();
+        if let Some(entry) = self.table.probe(hash) {
+            if ply > 0 && u32::from(entry.depth) >= depth {
+                // only cut off when the score falls outside the window, so the principal
+                // variation is always searched and filled in
+                let score = score_from_table(entry.score, ply);
+                let cutoff = match entry.bound {
+                    Bound::Exact => score <= alpha || score >= beta,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if cutoff {
+                    return score;
+                }
+            }
+            if let Some(index) = entry
+                .best_move
+                .map(usize::from)
+                .filter(|i| *i < moves.len())
+            {
+                order[..=index].rotate_right(1);
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best_index = None;
+        let mut child_pv = vec![];
+        self.path.push(hash);
+        for index in order {
+            let mv = &moves[index];
+            let score = -self.negamax(
+                &position.make_move(mv),
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+                &mut child_pv,
+            );
+            if self.aborted {
+                self.path.pop();
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                best_index = Some(index);
+                pv.clear();
+                pv.push(mv.clone());
+                pv.append(&mut child_pv);
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        self.path.pop();
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.store(
+            hash,
+            depth as u8,
+            score_to_table(alpha, ply),
+            bound,
+            best_index.and_then(|index| u8::try_from(index).ok()),
+        );
+        return alpha;
+    }
+
+    /// Keep playing out captures past the depth limit until the side to move has no jump, so
+    /// a position is never evaluated in the middle of an exchange. Captures are mandatory, so
+    /// the side to move can't decline them by taking the static evaluation instead.
+    fn quiescence(
+        &mut self,
+        position: &Position,
+        ply: u32,
+        remaining: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        let moves = position.legal_moves();
+        if moves.is_empty() {
+            // the side to move is blocked or out of pieces
+            return -(WIN_SCORE - ply as i32);
+        }
+        let (jumps, _) = position.board.find_available_jumps(position.turn);
+        if jumps.is_empty() || remaining == 0 || ply >= MAX_PLY {
+            return evaluate_with_weights(&position.board, position.turn, &self.options.weights);
+        }
+        for mv in moves {
+            if self.visit_node() {
+                return 0;
+            }
+            let score = -self.quiescence(
+                &position.make_move(&mv),
+                ply + 1,
+                remaining - 1,
+                -beta,
+                -alpha,
+            );
+            if self.aborted {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        return alpha;
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+/// Depth searched when no limit is given
+const DEFAULT_DEPTH: u32 = 8;
+
+/// Bounds on how much work a search may do, the search ends when the first one is reached.
+/// Without any limit the search only ends when it's stopped or reaches its deepest ply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>, // last iteration of iterative deepening, in plies
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,  // fixed time to spend on the move
+    pub time_left: Option<Duration>, // time left on the side to move's clock
+    pub increment: Duration,         // time added to the clock after each move
+}
+
+impl SearchLimits {
+    pub fn init() -> SearchLimits {
+        return SearchLimits::init_with_depth(DEFAULT_DEPTH);
+    }
+
+    pub fn init_with_depth(depth: u32) -> SearchLimits {
+        return SearchLimits {
+            depth: Some(depth),
+            nodes: None,
+            movetime: None,
+            time_left: None,
+            increment: Duration::ZERO,
+        };
+    }
+}
@@ -0,0 +1,84 @@
+use super::SearchLimits;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Moves the remaining clock time is expected to last for
+const MOVES_TO_GO: u32 = 30;
+/// Time kept back from the clock to cover sending the move
+const SAFETY_MARGIN: Duration = Duration::from_millis(50);
+/// Times past its allotted time a search may run to finish an iteration
+const HARD_LIMIT_FACTOR: u32 = 4;
+
+/// Flag shared between threads telling a running search to stop
+#[derive(Debug, Clone, Default)]
+pub struct StopSignal {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopSignal {
+    pub fn init() -> StopSignal {
+        return StopSignal::default();
+    }
+
+    /// Tell the search to stop, it returns the best move found so far
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        return self.stopped.load(Ordering::Relaxed);
+    }
+
+    /// Clear a stop request so the next search runs
+    pub fn reset(&self) {
+        self.stopped.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Decides how long a search may run from its limits and a stop signal
+#[derive(Debug)]
+pub struct TimeManager {
+    start: Instant,
+    soft_limit: Option<Duration>, // no new iteration is started after this
+    hard_limit: Option<Duration>, // the search is stopped part way through after this
+    node_limit: Option<u64>,
+    stop_signal: StopSignal,
+}
+
+impl TimeManager {
+    /// Start timing a search
+    pub fn init(limits: &SearchLimits, stop_signal: StopSignal) -> TimeManager {
+        let mut soft_limit = limits.movetime;
+        let mut hard_limit = limits.movetime;
+        if let Some(time_left) = limits.time_left {
+            let available = time_left.saturating_sub(SAFETY_MARGIN);
+            let allotted = (time_left / MOVES_TO_GO + limits.increment * 3 / 4).min(available);
+            let maximum = (allotted * HARD_LIMIT_FACTOR).min(available);
+            soft_limit = Some(soft_limit.map_or(allotted, |limit| limit.min(allotted)));
+            hard_limit = Some(hard_limit.map_or(maximum, |limit| limit.min(maximum)));
+        }
+        return TimeManager {
+            start: Instant::now(),
+            soft_limit,
+            hard_limit,
+            node_limit: limits.nodes,
+            stop_signal,
+        };
+    }
+
+    /// Check if there is time left to start another iteration
+    pub fn can_start_iteration(&self, nodes: u64) -> bool {
+        let elapsed = self.start.elapsed();
+        return !self.should_stop(nodes) && self.soft_limit.is_none_or(|limit| elapsed < limit);
+    }
+
+    /// Check if the search has to stop immediately
+    pub fn should_stop(&self, nodes: u64) -> bool {
+        return self.stop_signal.is_stopped()
+            || self.node_limit.is_some_and(|limit| nodes >= limit)
+            || self
+                .hard_limit
+                .is_some_and(|limit| self.start.elapsed() >= limit);
+    }
+}
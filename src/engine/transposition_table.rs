@@ -0,0 +1,138 @@
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Stored in place of a move index when an entry has no best move
+const NO_MOVE: u8 = u8::MAX;
+
+/// How a stored score relates to the true score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower, // the search failed high, the true score is at least this
+    Upper, // the search failed low, the true score is at most this
+}
+
+/// Result of searching a position, stored so it doesn't have to be searched again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry {
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    pub best_move: Option<u8>, // index into the position's legal moves
+    generation: u8,            // search the entry was stored in
+}
+
+impl TableEntry {
+    /// Pack the entry into 64 bits: score, depth, bound, best move and generation
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        return u64::from(self.score as u32)
+            | u64::from(self.depth) << 32
+            | bound << 40
+            | u64::from(self.best_move.unwrap_or(NO_MOVE)) << 48
+            | u64::from(self.generation) << 56;
+    }
+
+    fn unpack(data: u64) -> TableEntry {
+        let best_move = (data >> 48) as u8;
+        return TableEntry {
+            score: data as u32 as i32,
+            depth: (data >> 32) as u8,
+            bound: match (data >> 40) as u8 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+            best_move: (best_move != NO_MOVE).then_some(best_move),
+            generation: (data >> 56) as u8,
+        };
+    }
+}
+
+/// A slot holds an entry's packed data along with its key XORed with that data, so an entry
+/// torn by two threads writing at once fails the key check instead of being read back wrong
+#[derive(Debug, Default)]
+struct Slot {
+    checked_key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Fixed size hash table of searched positions indexed by zobrist hash. Probes and stores
+/// take a shared reference so every search thread can use the same table without locking.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    generation: u8,
+}
+
+impl TranspositionTable {
+    /// Create a table using at most `size_mb` megabytes. The number of entries is rounded down
+    /// to a power of two so an entry's index is the low bits of its key.
+    pub fn init(size_mb: usize) -> TranspositionTable {
+        let capacity = size_mb.saturating_mul(1024 * 1024) / size_of::<Slot>();
+        let slot_count = match capacity {
+            0 => 1,
+            capacity => 1 << capacity.ilog2(),
+        };
+        return TranspositionTable {
+            slots: (0..slot_count).map(|_| Slot::default()).collect(),
+            generation: 0,
+        };
+    }
+
+    /// Mark the start of a new search, entries from earlier searches become the first to be
+    /// replaced
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn get_slot(&self, key: u64) -> &Slot {
+        return &self.slots[key as usize & (self.slots.len() - 1)];
+    }
+
+    /// Read a slot's entry along with the key it was stored for
+    fn read(slot: &Slot) -> Option<(u64, TableEntry)> {
+        let data = slot.data.load(Ordering::Relaxed);
+        if data == 0 {
+            return None;
+        }
+        let key = slot.checked_key.load(Ordering::Relaxed) ^ data;
+        return Some((key, TableEntry::unpack(data)));
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        return TranspositionTable::read(self.get_slot(key))
+            .filter(|(entry_key, _)| *entry_key == key)
+            .map(|(_, entry)| entry);
+    }
+
+    /// Store a search result. The slot's current entry is kept if it was stored by this search
+    /// at a greater depth for a different position, otherwise it's replaced.
+    pub fn store(&self, key: u64, depth: u8, score: i32, bound: Bound, best_move: Option<u8>) {
+        let slot = self.get_slot(key);
+        let replace = match TranspositionTable::read(slot) {
+            None => true,
+            Some((current_key, current)) => {
+                current_key == key
+                    || current.generation != self.generation
+                    || depth >= current.depth
+            }
+        };
+        if replace {
+            let data = TableEntry {
+                score,
+                depth,
+                bound,
+                best_move,
+                generation: self.generation,
+            }
+            .pack();
+            slot.checked_key.store(key ^ data, Ordering::Relaxed);
+            slot.data.store(data, Ordering::Relaxed);
+        }
+    }
+}
@@ -1,56 +1,74 @@
 /// private modules only accessable within the `mod game`
 mod checkers_board;
+mod checkers_move;
+mod clock;
+mod game_result;
+mod move_history;
+mod move_result;
+mod pdn;
+mod side;
+mod square;
 mod utils;
 
-use checkers_board::CheckersBoard;
-use std::borrow::BorrowMut;
-use std::sync::{Arc, Mutex, MutexGuard};
-use utils::add;
-
-// default empty position string
-pub const EMPTY_POS: &str = "empty";
-
-#[derive(Debug)]
+use checkers_board::BOARD_SIZE;
+pub use checkers_board::{CheckersBoard, FenError};
+pub use checkers_move::Move;
+pub use clock::{Clock, TimeControl};
+pub use game_result::{DrawReason, DrawRules, GameResult};
+use move_history::HistoryEntry;
+pub use move_result::{MoveError, MoveOutcome};
+pub use pdn::{PdnError, PdnGame};
+pub use side::Side;
+pub use square::Square;
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
 pub struct Checkers {
-    player1: String,
-    player2: String,
+    player1: Option<String>, // plays dark
+    player2: Option<String>, // plays light
     board: CheckersBoard,
-    turn: String,
-    completed: bool,
-    winner: String,
+    turn: Side,
+    result: GameResult,
+    draw_rules: DrawRules,
+    position_counts: HashMap<u64, u32>, // times each position hash has occurred
+    quiet_moves: u32, // moves since the last capture or man move, counting both players
+    history: Vec<HistoryEntry>,
+    redo_moves: Vec<Move>, // moves taken back with `undo_move`, most recent last
+    clock: Option<Clock>,  // None for an untimed game
+    start_fen: Option<String>, // position the game started from, None for the usual start
 }
 
 impl Checkers {
     pub fn init() -> Checkers {
+        return Checkers::init_with_draw_rules(DrawRules::init());
+    }
+
+    pub fn init_with_draw_rules(draw_rules: DrawRules) -> Checkers {
         let checkers = Checkers {
-            player1: EMPTY_POS.to_string(),
-            player2: EMPTY_POS.to_string(),
+            player1: None,
+            player2: None,
             board: CheckersBoard::init(),
-            turn: EMPTY_POS.to_string(),
-            completed: false,
-            winner: EMPTY_POS.to_string(),
+            turn: Side::Dark,
+            result: GameResult::Ongoing,
+            draw_rules,
+            position_counts: HashMap::new(),
+            quiet_moves: 0,
+            history: vec![],
+            redo_moves: vec![],
+            clock: None,
+            start_fen: None,
         };
         return checkers;
     }
 
     pub fn setup_players(&mut self, player1: String, player2: String) -> Option<bool> {
-        // setup players
-        self.set_player1(&player1);
-        self.set_player2(&player2);
-        // set turn
-        let p1 = self.get_player1();
-        let p2 = self.get_player2();
-        self.set_turn(&p1);
-        // setup player sides
-        self.assign_side(0, &p1);
-        self.assign_side(7, &p2);
-        // init board pieces
-        self.initialize_board();
+        // setup players, player1 plays dark which always moves first
+        self.player1 = Some(player1);
+        self.player2 = Some(player2);
+        self.restart();
 
         // ensure everything is setup properly
-        if self.get_player1() == EMPTY_POS || self.get_player2() == EMPTY_POS {
-            return None;
-        }
         if !self.is_ready_to_start() {
             return None;
         }
@@ -58,255 +76,415 @@ impl Checkers {
         return Some(true);
     }
 
+    /// Put the game back to the usual starting position, with the clocks reset
+    fn restart(&mut self) {
+        self.turn = Side::Dark;
+        self.result = GameResult::Ongoing;
+        // init board pieces
+        self.initialize_board();
+        // start tracking positions for repetitions
+        self.position_counts.clear();
+        self.quiet_moves = 0;
+        self.history.clear();
+        self.redo_moves.clear();
+        self.start_fen = None;
+        self.record_position();
+        self.restart_clock();
+    }
+
+    /// Set up the players of a position or game that was loaded before they were known,
+    /// keeping it rather than restarting. The clocks start from the starting time.
+    pub fn setup_players_keeping_position(&mut self, player1: String, player2: String) {
+        self.player1 = Some(player1);
+        self.player2 = Some(player2);
+        self.restart_clock();
+    }
+
+    #[cfg(test)]
     pub fn init_with_players(&mut self, player1: String, player2: String) -> Option<bool> {
-        let mut checkers: Checkers = Checkers::init();
         return self.setup_players(player1, player2);
     }
 
-    pub fn get_player1(&self) -> String {
+    pub fn get_player1(&self) -> Option<String> {
         self.player1.clone()
     }
 
-    fn set_player1(&mut self, player_name: &String) {
-        self.player1 = player_name.to_lowercase().clone();
+    pub fn get_player2(&self) -> Option<String> {
+        self.player2.clone()
     }
 
-    pub fn get_player2(&self) -> String {
-        self.player2.clone()
+    /// Name of the player playing `side`
+    pub fn get_player(&self, side: Side) -> Option<String> {
+        match side {
+            Side::Dark => self.get_player1(),
+            Side::Light => self.get_player2(),
+        }
     }
 
-    fn set_player2(&mut self, player_name: &String) {
-        self.player2 = player_name.to_lowercase().clone();
+    pub fn get_turn(&self) -> Side {
+        self.turn
     }
 
-    pub fn get_turn(&self) -> String {
-        self.turn.clone()
+    pub fn get_board(&mut self) -> &mut CheckersBoard {
+        &mut self.board
     }
 
-    fn set_turn(&mut self, player_name: &String) {
-        let player_lower = player_name.to_lowercase();
-        if self.player1 != player_lower && self.player2 != player_lower {
-            return;
-        }
-        self.turn = player_name.to_lowercase().clone();
+    /// Play the game with clocks, they start when the players are set up
+    pub fn set_time_control(&mut self, time_control: TimeControl) {
+        self.clock = Some(Clock::init(time_control));
     }
 
-    pub fn get_board(&mut self) -> &mut CheckersBoard {
-        &mut self.board
+    pub fn get_clock(&self) -> Option<&Clock> {
+        return self.clock.as_ref();
     }
 
-    pub fn is_completed(&self) -> bool {
-        return self.completed;
+    /// Put the clocks back to the starting time and run the clock of the side to move, once
+    /// the players are set up
+    fn restart_clock(&mut self) {
+        let has_players = self.player1.is_some() && self.player2.is_some();
+        if let Some(clock) = &mut self.clock {
+            clock.reset();
+            if has_players {
+                clock.start(self.turn, Instant::now());
+            }
+        }
+    }
+
+    /// Check if the time of the player to move has run out, losing them the game
+    ///
+    /// **Returns** - true if the game was lost on time just now
+    pub fn check_time(&mut self) -> bool {
+        let now = Instant::now();
+        let Some(clock) = &mut self.clock else {
+            return false;
+        };
+        if self.result != GameResult::Ongoing {
+            return false;
+        }
+        let Some(side) = clock.get_flagged(now) else {
+            return false;
+        };
+        clock.stop(now);
+        self.result = GameResult::WinOnTime(side.opponent());
+        return true;
     }
 
-    fn set_completed(&mut self, completed: bool) {
-        self.completed = completed;
+    pub fn is_completed(&self) -> bool {
+        return self.result != GameResult::Ongoing;
     }
 
     fn check_completed(&mut self) -> bool {
-        let p1 = self.board.get_player_pieces(self.get_player1().clone());
-        let p2 = self.board.get_player_pieces(self.get_player2().clone());
-        if p1.is_empty() {
-            self.completed = true;
-            self.winner = self.get_player2();
-        } else if p2.is_empty() {
-            self.completed = true;
-            self.winner = self.get_player1();
+        let dark = self.board.get_player_pieces(Side::Dark);
+        let light = self.board.get_player_pieces(Side::Light);
+        if dark.is_empty() {
+            self.result = GameResult::Win(Side::Light);
+        } else if light.is_empty() {
+            self.result = GameResult::Win(Side::Dark);
+        } else if self.board.find_legal_moves(self.turn).is_empty() {
+            // a player who is completely blocked on their turn loses
+            self.result = GameResult::Win(self.turn.opponent());
+        } else if self.draw_rules.repetitions > 0
+            && self.get_position_count() >= self.draw_rules.repetitions
+        {
+            self.result = GameResult::Draw(DrawReason::Repetition);
+        } else if self.draw_rules.move_limit > 0
+            && self.quiet_moves >= self.draw_rules.move_limit * 2
+        {
+            self.result = GameResult::Draw(DrawReason::MoveRule);
+        }
+        if self.is_completed() {
+            if let Some(clock) = &mut self.clock {
+                clock.stop(Instant::now());
+            }
         }
         return self.is_completed();
     }
 
-    pub fn get_winner(&self) -> String {
-        self.winner.clone()
+    pub fn get_result(&self) -> GameResult {
+        self.result.clone()
+    }
+
+    /// Zobrist hash identifying the current position, made up of the board and the player to
+    /// move
+    pub fn get_position_hash(&self) -> u64 {
+        return self.board.get_position_hash(self.turn);
+    }
+
+    /// Number of times the current position has occurred in this game
+    fn get_position_count(&self) -> u32 {
+        return *self
+            .position_counts
+            .get(&self.get_position_hash())
+            .unwrap_or(&0);
     }
 
-    fn assign_side(&mut self, side: usize, owner: &String) {
-        self.board.assign_side(side, owner);
+    fn record_position(&mut self) {
+        *self
+            .position_counts
+            .entry(self.get_position_hash())
+            .or_insert(0) += 1;
     }
 
     fn initialize_board(&mut self) {
-        let arst1 = self.get_player1();
-        let arst2 = self.get_player2();
-        self.board.initialize_board_pieces(arst1, arst2);
+        self.board.initialize_board_pieces();
     }
 
     pub fn is_ready_to_start(&self) -> bool {
-        return self.board.is_board_ownership_ready()
-            && self.board.is_board_pieces_ready()
-            && !self.is_completed();
+        return self.board.is_board_pieces_ready() && !self.is_completed();
+    }
+
+    /// Find every complete move available to the player whose turn it is.
+    ///
+    /// Jumps are mandatory, so when a capture is available only capturing moves are returned,
+    /// and multi-jump chains are returned as a single move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.is_completed() || self.player1.is_none() || self.player2.is_none() {
+            return vec![];
+        }
+        return self.board.find_legal_moves(self.turn);
     }
 
     /// Check that a submitted move is valid to make and make it.
     ///
-    /// E.g.:
-    /// 1. move a piece foward to unoccupied location
-    /// 2. jump a opponent piece in either diagonal direction
+    /// The move must be complete, so a multi-jump is submitted as one move listing every
+    /// landing square. Only the origin and landings of `mv` are used, the captures and
+    /// promotion are taken from the matching legal move.
     ///
-    /// **Returns** - a boolean indicating if the move is valid
-    pub fn move_piece(
-        &mut self,
-        player: String,
-        piece_cord: (usize, usize),
-        dest_cord: (usize, usize),
-    ) -> bool {
-        println!("Attempting to move {:?} to {:?}", piece_cord, dest_cord);
+    /// **Returns** - the move that was made, or the reason the move was rejected
+    pub fn move_piece(&mut self, side: Side, mv: &Move) -> Result<MoveOutcome, MoveError> {
+        println!("Attempting to move {}", mv);
 
+        self.check_time();
         if self.is_completed() {
-            return false;
+            return Err(MoveError::GameCompleted);
         }
 
-        if piece_cord.0 >= self.board.positions.len() || piece_cord.1 >= self.board.positions.len()
+        if let Some(cord) = mv
+            .path()
+            .into_iter()
+            .find(|cord| cord.0 >= BOARD_SIZE || cord.1 >= BOARD_SIZE)
         {
-            return false;
+            return Err(MoveError::OutOfBounds(cord));
         }
 
-        let selected_piece = self.board.positions[piece_cord.0][piece_cord.1]
-            .occupant
-            .clone();
-
-        // Validate the player who sent a move cmd can take their turn
-        if !self.turn.eq(&player) {
-            return false;
+        // Validate the side that sent a move cmd can take their turn
+        if self.turn != side {
+            return Err(MoveError::NotYourTurn);
         }
 
-        // Invalid move if player doesn't own the piece_cord
-        if selected_piece.owner != player {
-            return false;
+        // Invalid move if the side doesn't own the piece being moved
+        let selected_piece = self.board.get_piece(mv.origin);
+        if selected_piece.map(|piece| piece.owner) != Some(side) {
+            return Err(MoveError::NotYourPiece(mv.origin));
         }
 
-        let (all_jumpable_pieces, all_available_jumps) = self.board.find_available_jumps(&player);
-
-        // if a jump is available and the destination didn't match any found the move isn't valid
-        if !all_available_jumps.is_empty() {
-            if !all_available_jumps.contains_key(&piece_cord) {
-                return false;
-            }
-            // if a jump is available and the destination didn't match any found the move isn't valid
-            if all_available_jumps.contains_key(&piece_cord)
-                && !all_available_jumps
-                    .get(&piece_cord)
-                    .unwrap()
-                    .contains(&dest_cord)
-            {
-                return false;
-            }
-
-            if all_available_jumps.contains_key(&piece_cord)
-                && all_available_jumps
-                    .get(&piece_cord)
-                    .unwrap()
-                    .contains(&dest_cord)
-            {
-                // find index of matched movement
-                let index = all_available_jumps
-                    .get(&piece_cord)
-                    .unwrap()
-                    .iter()
-                    .position(|e| e == &dest_cord)
-                    .unwrap();
-                self.complete_piece_move(
-                    piece_cord,
-                    dest_cord,
-                    all_jumpable_pieces.get(&piece_cord).unwrap().get(index),
-                    false, // TODO update this to determine if turn should be flipped
-                );
-                // check new state of board if a jump is available where the player went
-                let (all_jumpable_pieces2, all_available_jumps2) =
-                    self.board.find_available_jumps(&player);
-                if !all_available_jumps2.contains_key(&dest_cord) {
-                    // toggle turn there is no double jump available
-                    self.toggle_turn();
-                }
-
-                return true;
-            }
+        // if a jump is available the selected piece has to be one of the jumping pieces
+        let (_, all_available_jumps) = self.board.find_available_jumps(side);
+        if !all_available_jumps.is_empty() && !all_available_jumps.contains_key(&mv.origin) {
+            return Err(MoveError::CaptureMandatory);
+        }
 
-            // hit weird case?
-            // shouldn't hit this but if we do just return false
-            return false;
+        let legal_moves = self.legal_moves();
+        if let Some(legal_move) = legal_moves.iter().find(|m| m.same_path(mv)) {
+            // a new move replaces any moves that were taken back
+            self.redo_moves.clear();
+            self.complete_move(legal_move);
+            return Ok(MoveOutcome {
+                played: legal_move.clone(),
+                next_turn: self.get_turn(),
+                completed: self.is_completed(),
+            });
         }
 
-        // no jumps available
+        // the submitted jumps are the start of a longer chain
+        if legal_moves.iter().any(|m| {
+            m.origin == mv.origin
+                && !mv.landings.is_empty()
+                && m.landings.len() > mv.landings.len()
+                && m.landings.starts_with(&mv.landings)
+        }) {
+            return Err(MoveError::IncompleteCapture);
+        }
+        return Err(MoveError::IllegalMove);
+    }
 
-        // IF VALID DIAGONAL MOVE THEN GET IT BROTHER
-        if (add(piece_cord.0, selected_piece.direction)) == dest_cord.0
-            && piece_cord.1.abs_diff(dest_cord.1) == 1
-        {
-            self.complete_piece_move(piece_cord, dest_cord, Option::None, true);
-            return true;
-        } else if self.board.positions[piece_cord.0][piece_cord.1]
-            .occupant
-            .kinged
-            && (add(piece_cord.0, -selected_piece.direction)) == dest_cord.0
-            && piece_cord.1.abs_diff(dest_cord.1) == 1
-        {
-            self.complete_piece_move(piece_cord, dest_cord, Option::None, true);
-            return true;
+    /// Apply a legal move to the board as a single turn and pass the turn to the other player.
+    fn complete_move(&mut self, mv: &Move) {
+        let moved_piece = self.board.get_piece(mv.origin).unwrap();
+        let now = Instant::now();
+        self.history.push(HistoryEntry {
+            played: mv.clone(),
+            moved_piece,
+            captured: mv
+                .captures
+                .iter()
+                .filter_map(|cord| self.board.get_piece(*cord))
+                .collect(),
+            quiet_moves: self.quiet_moves,
+            result: self.get_result(),
+            clock: self.clock.clone().map(|mut clock| {
+                clock.stop(now);
+                clock
+            }),
+        });
+
+        // captures and man moves can't be undone, reset the move rule count
+        if mv.is_capture() || !moved_piece.kinged {
+            self.quiet_moves = 0;
+        } else {
+            self.quiet_moves += 1;
         }
-        return false;
+        self.board.apply_move(mv);
+        if let Some(clock) = &mut self.clock {
+            clock.complete_turn(now);
+        }
+        self.toggle_turn();
+        self.record_position();
+        self.check_completed();
     }
 
-    /// Perform the actual shifting of pieces related to a checkers piece movement. This includes
-    /// normal diagonal movement, jump diagonal movement, and n jumps after.
+    /// Take back the last move made, restoring the board, turn, result and clocks from before
+    /// it.
     ///
-    /// **Params**:
+    /// **Returns** - the move that was taken back, or None if no moves have been made
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let entry = self.history.pop()?;
+        if let Some(count) = self.position_counts.get_mut(&self.get_position_hash()) {
+            *count -= 1;
+        }
+        self.board
+            .unmake_move(&entry.played, &entry.moved_piece, &entry.captured);
+        self.toggle_turn();
+        self.quiet_moves = entry.quiet_moves;
+        self.result = entry.result;
+        // the side the move is taken back for is on the clock again, with the time it had
+        self.clock = entry.clock;
+        if let Some(clock) = &mut self.clock {
+            if self.result == GameResult::Ongoing {
+                clock.start(self.turn, Instant::now());
+            }
+        }
+        self.redo_moves.push(entry.played.clone());
+        return Some(entry.played);
+    }
+
+    /// Make the last move taken back with `undo_move` again.
     ///
-    /// * piece_cord - the piece selected for movement
-    /// * dest_cord - the suggested new location of piece_cord
-    /// * jumped_piece_cord - if a jump was performed this is the location of the piece which was jumped
-    /// * toggle_turn - indicates if the players turn is over
+    /// **Returns** - the move that was made, or None if there is nothing to redo
+    pub fn redo_move(&mut self) -> Option<Move> {
+        let mv = self.redo_moves.pop()?;
+        self.complete_move(&mv);
+        return Some(mv);
+    }
+
+    /// Check if there is a move taken back with `undo_move` to make again
+    pub fn can_redo(&self) -> bool {
+        return !self.redo_moves.is_empty();
+    }
+
+    /// Every move made so far this game, in order
+    pub fn get_move_history(&self) -> Vec<Move> {
+        return self
+            .history
+            .iter()
+            .map(|entry| entry.played.clone())
+            .collect();
+    }
+
+    /// Restart the game from a position given as a FEN string, see
+    /// `CheckersBoard::init_from_fen`. The players stay the same and the move history is cleared.
+    pub fn load_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let (board, turn) = CheckersBoard::init_from_fen(fen)?;
+        self.board = board;
+        self.turn = turn;
+        self.result = GameResult::Ongoing;
+        self.position_counts.clear();
+        self.quiet_moves = 0;
+        self.history.clear();
+        self.redo_moves.clear();
+        self.start_fen = Some(self.get_fen());
+        self.record_position();
+        self.restart_clock();
+        self.check_completed();
+        return Ok(());
+    }
+
+    /// FEN string of the current position
+    pub fn get_fen(&self) -> String {
+        return self.board.get_fen(self.turn);
+    }
+
+    /// Start a new game from a PDN record, replaying the moves of its main line from the
+    /// position in its FEN tag, or the usual starting position without one. Players that have
+    /// been set up keep their names, otherwise they are named after the record's Black and
+    /// White tags.
     ///
-    /// **Returns** - boolean indicating of everything worked as expected
-    fn complete_piece_move(
-        &mut self,
-        piece_cord: (usize, usize),
-        dest_cord: (usize, usize),
-        jumped_piece_cord: Option<&(usize, usize)>,
-        toggle_turn: bool,
-    ) -> bool {
-        let mut moving_piece = self.board.positions[piece_cord.0][piece_cord.1]
-            .occupant
-            .clone();
-        moving_piece.loc = dest_cord;
-        self.board.positions[dest_cord.0][dest_cord.1].occupant = moving_piece;
-        let copy_moved_piece = self.board.positions[dest_cord.0][dest_cord.1]
-            .occupant
-            .clone();
-        self.board.remove_piece(piece_cord);
-
-        if let Some(jumped_piece_cord) = jumped_piece_cord {
-            self.board.remove_piece(*jumped_piece_cord);
+    /// The record is replayed on a copy of the game, so the game is left as it was if any of
+    /// it fails to load.
+    pub fn load_pdn(&mut self, pdn: &PdnGame) -> Result<(), PdnError> {
+        let mut loaded = self.clone();
+        let named_from_tags = loaded.player1.is_none() || loaded.player2.is_none();
+        if named_from_tags {
+            loaded.player1 = Some(pdn.get_tag("Black").unwrap_or("dark").to_string());
+            loaded.player2 = Some(pdn.get_tag("White").unwrap_or("light").to_string());
         }
-
-        // check if piece should get kinged
-        if !copy_moved_piece.kinged
-            && self.board.positions[dest_cord.0][dest_cord.1].owner != EMPTY_POS
-            && self.board.positions[dest_cord.0][dest_cord.1].owner != copy_moved_piece.owner
-        {
-            self.board.positions[dest_cord.0][dest_cord.1]
-                .occupant
-                .kinged = true;
-            println!("Kinged piece ({}, {})", dest_cord.0, dest_cord.1);
+        match pdn.get_tag("FEN") {
+            Some(fen) => loaded.load_fen(fen).map_err(PdnError::InvalidFen)?,
+            None => loaded.restart(),
         }
-
-        if toggle_turn {
-            self.toggle_turn();
+        for pdn_move in &pdn.mainline.moves {
+            if loaded.is_completed() {
+                return Err(PdnError::MoveFailed(
+                    pdn_move.to_string(),
+                    MoveError::GameCompleted,
+                ));
+            }
+            let matching: Vec<Move> = loaded
+                .legal_moves()
+                .into_iter()
+                .filter(|mv| pdn_move.matches(mv))
+                .collect();
+            let mv = match matching.as_slice() {
+                [mv] => mv.clone(),
+                [] => return Err(PdnError::UnknownMove(pdn_move.to_string())),
+                _ => return Err(PdnError::AmbiguousMove(pdn_move.to_string())),
+            };
+            loaded
+                .move_piece(loaded.turn, &mv)
+                .map_err(|error| PdnError::MoveFailed(pdn_move.to_string(), error))?;
+        }
+        if named_from_tags {
+            // the players named in the record aren't playing, the clocks wait for those who are
+            if let Some(clock) = &mut loaded.clock {
+                clock.reset();
+            }
         }
+        *self = loaded;
+        return Ok(());
+    }
 
-        self.check_completed();
-        return true;
+    /// Record of the game so far as PDN, with the position it started from in a FEN tag if it
+    /// didn't start from the usual position
+    pub fn to_pdn(&self) -> PdnGame {
+        let mut pdn = PdnGame::init(
+            &self.player1.clone().unwrap_or(Side::Dark.to_string()),
+            &self.player2.clone().unwrap_or(Side::Light.to_string()),
+            &self.get_move_history(),
+            &self.result,
+        );
+        if let Some(fen) = &self.start_fen {
+            pdn.tags.push((String::from("SetUp"), String::from("1")));
+            pdn.tags.push((String::from("FEN"), fen.clone()));
+        }
+        return pdn;
     }
 
     fn toggle_turn(&mut self) {
-        if self.turn == self.player1 {
-            self.turn = self.player2.clone();
-        } else {
-            self.turn = self.player1.clone();
-        }
+        self.turn = self.turn.opponent();
     }
 
     pub fn print_board(&self) {
-        println!("{}", self.board.to_string());
+        println!("{}", self.board);
         println!("Turn: {}", self.get_turn());
     }
 }
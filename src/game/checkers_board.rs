@@ -1,392 +1,409 @@
 mod checkers_piece;
-mod position;
+mod fen;
+mod zobrist;
 
-use super::utils::add;
+use super::checkers_move::Move;
+use super::side::Side;
 use super::utils::EMPTY_POS;
-use checkers_piece::CheckerPiece;
-use position::Position;
+pub use checkers_piece::CheckerPiece;
+pub use fen::FenError;
 use std::collections::HashMap;
+use std::fmt;
 use std::format;
 use std::vec;
 
-#[derive(Debug)]
+/// Map of piece locations to a list of related locations (jumped pieces or landing squares)
+pub type JumpMap = HashMap<(usize, usize), Vec<(usize, usize)>>;
+
+/// Number of rows and columns on the board
+pub const BOARD_SIZE: usize = 8;
+
+// The 32 playable squares are stored as bits, numbered four to a row from the top left so a
+// square's bit is `row * 4 + col / 2`. Even rows start on column 1 and odd rows on column 0,
+// which changes how far a diagonal step shifts a square's bit.
+const EVEN_ROWS: u32 = 0x0F0F_0F0F;
+const ODD_ROWS: u32 = 0xF0F0_F0F0;
+const LEFT_SQUARES: u32 = 0x1111_1111; // left most playable square of each row
+const RIGHT_SQUARES: u32 = 0x8888_8888; // right most playable square of each row
+const FIRST_ROW: u32 = 0x0000_000F;
+const LAST_ROW: u32 = 0xF000_0000;
+const DARK_START: u32 = 0x0000_0FFF; // rows 0-2
+const LIGHT_START: u32 = 0xFFF0_0000; // rows 5-7
+
+/// Move every square in `squares` one diagonal step, dropping any that would leave the board
+fn step(squares: u32, row_direction: i32, col_direction: i32) -> u32 {
+    match (row_direction > 0, col_direction > 0) {
+        (true, false) => ((squares & EVEN_ROWS) << 4) | ((squares & ODD_ROWS & !LEFT_SQUARES) << 3),
+        (true, true) => ((squares & EVEN_ROWS & !RIGHT_SQUARES) << 5) | ((squares & ODD_ROWS) << 4),
+        (false, false) => {
+            ((squares & EVEN_ROWS) >> 4) | ((squares & ODD_ROWS & !LEFT_SQUARES) >> 5)
+        }
+        (false, true) => {
+            ((squares & EVEN_ROWS & !RIGHT_SQUARES) >> 3) | ((squares & ODD_ROWS) >> 4)
+        }
+    }
+}
+
+/// Location of a playable square's bit
+fn square_to_cord(square: u32) -> (usize, usize) {
+    let row = (square / 4) as usize;
+    let col = (square % 4) as usize * 2 + (1 - row % 2);
+    return (row, col);
+}
+
+/// Bit of the playable square at a location, None for blocked or off board locations
+fn cord_to_square(cord: (usize, usize)) -> Option<u32> {
+    if cord.0 >= BOARD_SIZE || cord.1 >= BOARD_SIZE || (cord.0 + cord.1).is_multiple_of(2) {
+        return None;
+    }
+    return Some((cord.0 * 4 + cord.1 / 2) as u32);
+}
+
+/// Iterate over the index of every set bit
+fn squares(mut mask: u32) -> impl Iterator<Item = u32> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let square = mask.trailing_zeros();
+        mask &= mask - 1;
+        Some(square)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CheckersBoard {
-    pub positions: Vec<Vec<Position>>,
+    dark: u32,
+    light: u32,
+    kings: u32,
+    hash: u64, // zobrist key of every piece on the board, kept up to date as pieces move
 }
 
 impl CheckersBoard {
     pub fn init() -> CheckersBoard {
-        let mut board: Vec<Vec<Position>> = vec![];
-        let mut is_blocked = true;
-        for i in 0..8 {
-            let mut row: Vec<Position> = vec![];
-            for j in 0..8 {
-                let mut pos = Position::init_with_loc((i, j));
-                pos.blocked = is_blocked;
-                row.push(pos);
-                is_blocked = !is_blocked;
-            }
-            board.push(row);
-            is_blocked = !is_blocked;
+        return CheckersBoard {
+            dark: 0,
+            light: 0,
+            kings: 0,
+            hash: 0,
+        };
+    }
+
+    fn get_side_mask(&self, side: Side) -> u32 {
+        match side {
+            Side::Dark => self.dark,
+            Side::Light => self.light,
         }
-        return CheckersBoard { positions: board };
     }
 
-    fn get_board_max_cord(&self) -> i32 {
-        return (self.positions.len() - 1) as i32;
+    fn get_empty_mask(&self) -> u32 {
+        return !(self.dark | self.light);
+    }
+
+    /// Row where `side`'s men get kinged
+    fn get_king_row_mask(side: Side) -> u32 {
+        match side {
+            Side::Dark => LAST_ROW,
+            Side::Light => FIRST_ROW,
+        }
     }
 
     pub fn get_board_as_string(&self) -> String {
-        let board_string_list: Vec<String> = self
-            .positions
-            .iter()
-            .map(|c| c.iter().map(|d| d.clone().to_string() + ", ").collect())
-            .collect();
-        let board_string: String = board_string_list
-            .iter()
-            .map(|s| format!("[{}],", &s[0..s.len() - 2]))
+        let rows: Vec<String> = (0..BOARD_SIZE)
+            .map(|row| {
+                let cols: Vec<String> = (0..BOARD_SIZE)
+                    .map(|col| {
+                        if cord_to_square((row, col)).is_none() {
+                            return String::from("X");
+                        }
+                        format!("({}, {}, {})", row, col, self.get_owner_name((row, col)))
+                    })
+                    .collect();
+                format!("[{}]", cols.join(", "))
+            })
             .collect();
-        format!("[{}]", &board_string[0..board_string.len() - 1])
+        format!("[{}]", rows.join(","))
     }
 
-    pub fn to_string(&self) -> String {
-        let mut board_string = String::new();
-        for row in &self.positions {
-            board_string.push_str("[");
-            for (i, col) in row.iter().enumerate() {
-                if col.blocked {
-                    board_string.push_str(
-                        format!(" {piece_cord:?} ----- ", piece_cord = col.occupant.loc,).as_str(),
-                    );
-                } else {
-                    board_string.push_str(
-                        format!(
-                            " {piece_cord:?} {owner:>5} ",
-                            owner = col.occupant.owner,
-                            piece_cord = col.occupant.loc
-                        )
-                        .as_str(),
-                    );
-                }
-                if i < row.len() - 1 {
-                    board_string.push_str("|");
-                }
-            }
-            board_string.push_str("]\n");
+    /// Name of the side with a piece at a location, or the empty position string
+    fn get_owner_name(&self, loc: (usize, usize)) -> String {
+        match self.get_piece(loc) {
+            Some(piece) => piece.owner.to_string(),
+            None => EMPTY_POS.to_string(),
         }
-        return board_string;
     }
 
-    /// Validate that CheckersBoard is correctly setup for a game
-    pub fn is_board_ownership_ready(&self) -> bool {
-        // check that the first row has a distinct owner
-        let mut check_first_row = false;
-        let mut first_row_owner = EMPTY_POS.to_string();
-        let first_row = &self.positions[0];
-        for col in first_row {
-            if col.owner == EMPTY_POS {
-                // fail validation
-                check_first_row = false;
-                break;
-            }
-            if first_row_owner != EMPTY_POS && first_row_owner != col.owner {
-                // validate that the first row's owner is consistent
-                check_first_row = false;
-                break;
-            }
-            if first_row_owner == EMPTY_POS {
-                // if first_row_owner isn't set, lets set it for our check above
-                first_row_owner = col.owner.clone();
-            }
-            check_first_row = true;
+    /// Zobrist hash of the board with `turn` to move, used to detect repeated positions
+    pub fn get_position_hash(&self, turn: Side) -> u64 {
+        return self.hash ^ zobrist::turn_key(turn);
+    }
+
+    /// Piece at a location, if there is one
+    pub fn get_piece(&self, loc: (usize, usize)) -> Option<CheckerPiece> {
+        let bit = 1 << cord_to_square(loc)?;
+        let owner = if self.dark & bit != 0 {
+            Side::Dark
+        } else if self.light & bit != 0 {
+            Side::Light
+        } else {
+            return None;
+        };
+        let mut piece = CheckerPiece::init(owner, loc);
+        piece.kinged = self.kings & bit != 0;
+        return Some(piece);
+    }
+
+    /// Put a piece on the board, replacing anything already at its location
+    pub fn set_piece(&mut self, piece: &CheckerPiece) {
+        let Some(square) = cord_to_square(piece.loc) else {
+            return;
+        };
+        self.remove_square(square);
+        self.add_square(square, piece.owner, piece.kinged);
+    }
+
+    /// Put a piece on an empty square
+    fn add_square(&mut self, square: u32, owner: Side, kinged: bool) {
+        let bit = 1 << square;
+        match owner {
+            Side::Dark => self.dark |= bit,
+            Side::Light => self.light |= bit,
         }
-        // check that the last row has a distinct owner
-        let mut check_last_row = false;
-        let mut last_row_owner = EMPTY_POS.to_string();
-        let last_row = &self.positions[self.positions.len() - 1];
-        for col in last_row {
-            if col.owner == EMPTY_POS {
-                // fail validation
-                check_last_row = false;
-                break;
-            }
-            if last_row_owner != EMPTY_POS && last_row_owner != col.owner {
-                // validate that the first row's owner is consistent
-                check_last_row = false;
-                break;
-            }
-            if last_row_owner == EMPTY_POS {
-                // if first_row_owner isn't set, lets set it for our check above
-                last_row_owner = col.owner.clone();
-            }
-            check_last_row = true;
+        if kinged {
+            self.kings |= bit;
         }
-        return check_first_row && check_last_row;
+        self.hash ^= zobrist::piece_key(square, owner, kinged);
     }
 
-    pub fn assign_side(&mut self, side: usize, owner: &String) {
-        for row_index in 0..self.positions.len() {
-            if row_index != side {
-                // if not side, skip logic
-                continue;
-            }
-            for col_index in 0..self.positions[row_index].len() {
-                self.positions[row_index][col_index].owner = owner.clone();
-            }
+    pub fn remove_piece(&mut self, piece_cord: (usize, usize)) {
+        if let Some(square) = cord_to_square(piece_cord) {
+            self.remove_square(square);
         }
     }
 
-    pub fn get_player_pieces(&self, player: String) -> Vec<CheckerPiece> {
-        let mut player_pieces: Vec<CheckerPiece> = vec![];
-        for row in &self.positions {
-            for col in row {
-                if player == col.occupant.owner {
-                    player_pieces.push(col.occupant.clone());
-                }
-            }
-        }
-        return player_pieces;
+    fn remove_square(&mut self, square: u32) {
+        let bit = 1 << square;
+        let owner = if self.dark & bit != 0 {
+            Side::Dark
+        } else if self.light & bit != 0 {
+            Side::Light
+        } else {
+            return;
+        };
+        self.hash ^= zobrist::piece_key(square, owner, self.kings & bit != 0);
+        let keep = !bit;
+        self.dark &= keep;
+        self.light &= keep;
+        self.kings &= keep;
+    }
+
+    pub fn get_player_pieces(&self, side: Side) -> Vec<CheckerPiece> {
+        return squares(self.get_side_mask(side))
+            .filter_map(|square| self.get_piece(square_to_cord(square)))
+            .collect();
     }
 
     pub fn is_board_pieces_ready(&self) -> bool {
-        let player1_pieces = self.get_player_pieces(self.positions[0][0].owner.clone());
-        let player2_pieces =
-            self.get_player_pieces(self.positions[self.positions.len() - 1][0].owner.clone());
-        if player1_pieces.len() != 12 || player2_pieces.len() != 12 {
-            // initial piece count should equal 16
+        if self.dark.count_ones() != 12 || self.light.count_ones() != 12 {
+            // initial piece count should equal 12
             return false;
         }
         return true;
     }
 
-    /// Initizlize pieces onto the game board based on information already setup.
-    /// This method requries side ownership to have already be assigned.
-    pub fn initialize_board_pieces(&mut self, player1: String, player2: String) {
-        // validate player1 & player2 aren't empty
-        if player1 == "" || player2 == "" {
-            return;
+    /// Initizlize pieces onto the game board, dark on the first three rows and light on the
+    /// last three.
+    pub fn initialize_board_pieces(&mut self) {
+        *self = CheckersBoard::init();
+        for square in squares(DARK_START) {
+            self.add_square(square, Side::Dark, false);
         }
-        // update board with CheckersPieces
-        for row_index in 0..self.positions.len() {
-            if row_index == 0 || row_index == 2 {
-                let mut col_index = 1;
-                while col_index < self.positions[row_index].len() {
-                    self.positions[row_index][col_index].occupant.owner = player1.clone();
-                    self.positions[row_index][col_index].occupant.direction = 1;
-                    col_index += 2;
-                }
-            } else if row_index == 1 {
-                let mut col_index = 0;
-                while col_index < self.positions[row_index].len() {
-                    self.positions[row_index][col_index].occupant.owner = player1.clone();
-                    self.positions[row_index][col_index].occupant.direction = 1;
-                    col_index += 2;
-                }
-            } else if row_index == 5 || row_index == 7 {
-                let mut col_index = 0;
-                while col_index < self.positions[row_index].len() {
-                    self.positions[row_index][col_index].occupant.owner = player2.clone();
-                    self.positions[row_index][col_index].occupant.direction = -1;
-                    col_index += 2;
-                }
-            } else if row_index == 6 {
-                let mut col_index = 1;
-                while col_index < self.positions[row_index].len() {
-                    self.positions[row_index][col_index].occupant.owner = player2.clone();
-                    self.positions[row_index][col_index].occupant.direction = -1;
-                    col_index += 2;
-                }
-            }
+        for square in squares(LIGHT_START) {
+            self.add_square(square, Side::Light, false);
         }
     }
 
-    /// Check if the given piece has an available jump in the given direction
-    fn check_if_move_is(
-        &self,
-        position_direction: &i32,
-        piece_loc: &(usize, usize),
-        piece_direction: &i32,
-        piece_owner: &String,
-    ) -> Option<(&Position, &Position)> {
-        // verify jump cords
-        let jp_y = piece_loc.0 as i32 + piece_direction;
-        let jp_x = piece_loc.1 as i32 + position_direction;
-        let jlp_y = piece_loc.0 as i32 + piece_direction * 2;
-        let jlp_x = piece_loc.1 as i32 + position_direction * 2;
-        if jp_y > self.get_board_max_cord()
-            || jp_x > self.get_board_max_cord()
-            || jlp_y > self.get_board_max_cord()
-            || jlp_x > self.get_board_max_cord()
-            || jp_y < 0
-            || jp_x < 0
-            || jlp_y < 0
-            || jlp_x < 0
-        {
-            return None;
-        }
-
-        let jump_position = &self.positions[jp_y.abs() as usize][jp_x.abs() as usize];
-        let jump_landing_position = &self.positions[jlp_y.abs() as usize][jlp_x.abs() as usize];
-
-        if jump_position.occupant.owner != "empty"
-            && jump_position.occupant.owner != *piece_owner
-            && jump_landing_position.occupant.owner == "empty"
-        {
-            return Some((jump_position, jump_landing_position));
+    /// Diagonal directions a piece can move in, kinged pieces can also move backwards
+    fn get_directions(side: Side, kinged: bool) -> Vec<(i32, i32)> {
+        let mut directions = vec![(side.direction(), -1), (side.direction(), 1)];
+        if kinged {
+            directions.push((-side.direction(), -1));
+            directions.push((-side.direction(), 1));
         }
-        return None;
+        return directions;
     }
 
-    pub fn find_available_jumps(
-        &self,
-        player: &String,
-    ) -> (
-        HashMap<(usize, usize), Vec<(usize, usize)>>,
-        HashMap<(usize, usize), Vec<(usize, usize)>>,
-    ) {
-        // get pieces for player
-        let player_pieces = &self.get_player_pieces(player.clone());
-        let mut avail_jump_pos: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
-        let mut avail_jump_landing_pos: HashMap<(usize, usize), Vec<(usize, usize)>> =
-            HashMap::new();
-        for piece in player_pieces {
-            let positions: Vec<i32> = vec![-1, 1];
-            for position_direction in positions {
-                if let Some((jp, jlp)) = self.check_if_move_is(
-                    &position_direction,
-                    &piece.loc,
-                    &piece.direction,
-                    &piece.owner,
-                ) {
-                    // available_jump_list.push(jump_position.clone());
-                    if !avail_jump_landing_pos.contains_key(&piece.loc) {
-                        avail_jump_pos.insert(piece.loc, vec![jp.occupant.loc.clone()]);
-                        avail_jump_landing_pos.insert(piece.loc, vec![jlp.occupant.loc.clone()]);
-                    } else {
-                        avail_jump_pos
-                            .get_mut(&piece.loc)
-                            .unwrap()
-                            .push(jlp.occupant.loc.clone());
-                        avail_jump_landing_pos
-                            .get_mut(&piece.loc)
-                            .unwrap()
-                            .push(jlp.occupant.loc.clone());
-                    }
-                }
-                if piece.kinged {
-                    if let Some((jp, jlp)) = self.check_if_move_is(
-                        &position_direction,
-                        &piece.loc,
-                        &piece.direction.wrapping_neg(), // inverse direction b/ kinged
-                        &piece.owner,
-                    ) {
-                        // available_jump_list.push(jump_position.clone());
-                        if !avail_jump_landing_pos.contains_key(&piece.loc) {
-                            avail_jump_pos.insert(piece.loc, vec![jp.occupant.loc.clone()]);
-                            avail_jump_landing_pos
-                                .insert(piece.loc, vec![jlp.occupant.loc.clone()]);
-                        } else {
-                            avail_jump_pos
-                                .get_mut(&piece.loc)
-                                .unwrap()
-                                .push(jlp.occupant.loc.clone());
-                            avail_jump_landing_pos
-                                .get_mut(&piece.loc)
-                                .unwrap()
-                                .push(jlp.occupant.loc.clone());
-                        }
-                    }
+    /// Find every single jump available to a player's pieces.
+    ///
+    /// **Returns** - two maps keyed by piece location, the first holding the locations of the
+    /// pieces that can be jumped and the second the matching landing locations
+    pub fn find_available_jumps(&self, side: Side) -> (JumpMap, JumpMap) {
+        let mut avail_jump_pos: JumpMap = HashMap::new();
+        let mut avail_jump_landing_pos: JumpMap = HashMap::new();
+        let opponents = self.get_side_mask(side.opponent());
+        let empty = self.get_empty_mask();
+        for square in squares(self.get_side_mask(side)) {
+            let bit = 1 << square;
+            for (row_direction, col_direction) in
+                CheckersBoard::get_directions(side, self.kings & bit != 0)
+            {
+                let jumped = step(bit, row_direction, col_direction) & opponents;
+                let landing = step(jumped, row_direction, col_direction) & empty;
+                if landing == 0 {
+                    continue;
                 }
+                let loc = square_to_cord(square);
+                avail_jump_pos
+                    .entry(loc)
+                    .or_default()
+                    .push(square_to_cord(jumped.trailing_zeros()));
+                avail_jump_landing_pos
+                    .entry(loc)
+                    .or_default()
+                    .push(square_to_cord(landing.trailing_zeros()));
             }
         }
         return (avail_jump_pos, avail_jump_landing_pos);
     }
 
-    /// Validate that a submitted move is valid to make.
-    /// Private method.
-    ///
-    /// E.g.:
-    /// 1. move a piece foward to unoccupied location
-    /// 2. jump a opponent piece in either diagonal direction
+    /// Find every complete move a player can make from the current position.
     ///
-    /// returns a boolean indicating if the move is valid
-    pub fn move_piece(
-        &mut self,
-        player: String,
-        piece_cord: (usize, usize),
-        dest_cord: (usize, usize),
-    ) -> bool {
-        println!("Attempting to move {:?} to {:?}", piece_cord, dest_cord);
-        if piece_cord.0 >= self.positions.len() || piece_cord.1 >= self.positions.len() {
-            return false;
+    /// Jumps are mandatory, so if any jump exists only jump chains are returned. A chain is
+    /// followed until the piece has no further jump or is kinged, which ends the move.
+    pub fn find_legal_moves(&self, side: Side) -> Vec<Move> {
+        let mut jump_moves: Vec<Move> = vec![];
+        for square in squares(self.get_side_mask(side)) {
+            self.find_jump_chains(
+                side,
+                square,
+                &mut Move::init(square_to_cord(square), vec![]),
+                &mut jump_moves,
+            );
         }
-
-        let selected_piece = self.positions[piece_cord.0][piece_cord.1].occupant.clone();
-        // let destination_piece = &self.board[dest_cord.0][dest_cord.1].occupant;
-
-        // Invalid move if player doesn't own the piece_cord
-        if selected_piece.owner != player {
-            return false;
+        if !jump_moves.is_empty() {
+            return jump_moves;
         }
 
-        let (all_jumpable_pieces, all_available_jumps) = self.find_available_jumps(&player);
-        if all_available_jumps.contains_key(&piece_cord)
-            && all_available_jumps
-                .get(&piece_cord)
-                .unwrap()
-                .contains(&dest_cord)
-        {
-            let index = all_available_jumps
-                .get(&piece_cord)
-                .unwrap()
-                .iter()
-                .position(|e| e == &dest_cord)
-                .unwrap();
-            // prepare moving piece
-            let mut moving_piece = self.positions[piece_cord.0][piece_cord.1].occupant.clone();
-            moving_piece.loc = dest_cord;
-            // remove starting loc
-            self.remove_piece(piece_cord);
-            // update landing loc
-            self.positions[dest_cord.0][dest_cord.1].occupant = moving_piece;
-            // remove jumped loc
-            self.remove_piece(
-                *all_jumpable_pieces
-                    .get(&piece_cord)
-                    .unwrap()
-                    .get(index)
-                    .unwrap(),
-            );
-            return true;
+        let mut moves: Vec<Move> = vec![];
+        let empty = self.get_empty_mask();
+        for square in squares(self.get_side_mask(side)) {
+            let bit = 1 << square;
+            let kinged = self.kings & bit != 0;
+            for (row_direction, col_direction) in CheckersBoard::get_directions(side, kinged) {
+                let dest = step(bit, row_direction, col_direction) & empty;
+                if dest == 0 {
+                    continue;
+                }
+                let mut step_move = Move::init(
+                    square_to_cord(square),
+                    vec![square_to_cord(dest.trailing_zeros())],
+                );
+                step_move.promotes = !kinged && dest & CheckersBoard::get_king_row_mask(side) != 0;
+                moves.push(step_move);
+            }
         }
+        return moves;
+    }
 
-        // if a jump is available and the destination didn't match any found the move isn't valid
-        if all_available_jumps.contains_key(&piece_cord)
-            && all_available_jumps
-                .get(&piece_cord)
-                .unwrap()
-                .contains(&dest_cord)
-        {
-            return false;
+    /// Recursively follow every jump available to the piece on `square`, pushing each finished
+    /// chain onto `chains`. Each jump is played out on a copy of the board so a jumped piece
+    /// can't be jumped twice and the square the piece left is free to land on again.
+    fn find_jump_chains(&self, side: Side, square: u32, chain: &mut Move, chains: &mut Vec<Move>) {
+        let bit = 1 << square;
+        let kinged = self.kings & bit != 0;
+        let opponents = self.get_side_mask(side.opponent());
+        let empty = self.get_empty_mask();
+        let mut jumped_any = false;
+        for (row_direction, col_direction) in CheckersBoard::get_directions(side, kinged) {
+            let jumped = step(bit, row_direction, col_direction) & opponents;
+            let landing = step(jumped, row_direction, col_direction) & empty;
+            if landing == 0 {
+                continue;
+            }
+            jumped_any = true;
+
+            let mut board = *self;
+            board.remove_square(square);
+            board.remove_square(jumped.trailing_zeros());
+            board.add_square(landing.trailing_zeros(), side, kinged);
+
+            chain
+                .landings
+                .push(square_to_cord(landing.trailing_zeros()));
+            chain.captures.push(square_to_cord(jumped.trailing_zeros()));
+            if !kinged && landing & CheckersBoard::get_king_row_mask(side) != 0 {
+                // being kinged ends the move
+                let mut finished = chain.clone();
+                finished.promotes = true;
+                chains.push(finished);
+            } else {
+                board.find_jump_chains(side, landing.trailing_zeros(), chain, chains);
+            }
+            chain.landings.pop();
+            chain.captures.pop();
+        }
+        if !jumped_any && chain.is_capture() {
+            chains.push(chain.clone());
         }
+    }
 
-        // IF VALID DIAGONAL MOVE THEN GET IT BROTHER
-        if (add(piece_cord.0, selected_piece.direction)) == dest_cord.0
-            && piece_cord.1.abs_diff(dest_cord.1) == 1
-        {
-            let mut moving_piece = self.positions[piece_cord.0][piece_cord.1].occupant.clone();
-            moving_piece.loc = dest_cord;
-            self.positions[dest_cord.0][dest_cord.1].occupant = moving_piece;
-            self.remove_piece(piece_cord);
-            return true;
+    /// Play a legal move on the board: relocate the piece, remove every jumped piece and king
+    /// the piece if the move promotes it.
+    pub fn apply_move(&mut self, mv: &Move) {
+        let Some(mut moving_piece) = self.get_piece(mv.origin) else {
+            return;
+        };
+        moving_piece.loc = mv.destination();
+        if mv.promotes {
+            moving_piece.kinged = true;
         }
+        self.remove_piece(mv.origin);
+        for jumped_piece_cord in &mv.captures {
+            self.remove_piece(*jumped_piece_cord);
+        }
+        self.set_piece(&moving_piece);
+    }
 
-        return false;
+    /// Take back a move made with `apply_move`, putting the moved piece back as it was and
+    /// returning every captured piece to the board.
+    pub fn unmake_move(
+        &mut self,
+        mv: &Move,
+        moved_piece: &CheckerPiece,
+        captured: &[CheckerPiece],
+    ) {
+        self.remove_piece(mv.destination());
+        self.set_piece(moved_piece);
+        for piece in captured {
+            self.set_piece(piece);
+        }
     }
+}
 
-    pub fn remove_piece(&mut self, piece_cord: (usize, usize)) {
-        // reset original piece
-        self.positions[piece_cord.0][piece_cord.1].occupant =
-            CheckerPiece::init_with_loc(piece_cord);
+impl fmt::Display for CheckersBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut board_string = String::new();
+        for row in 0..BOARD_SIZE {
+            board_string.push('[');
+            for col in 0..BOARD_SIZE {
+                let piece_cord = (row, col);
+                if cord_to_square(piece_cord).is_none() {
+                    board_string.push_str(format!(" {piece_cord:?} ----- ").as_str());
+                } else {
+                    board_string.push_str(
+                        format!(
+                            " {piece_cord:?} {owner:>5} ",
+                            owner = self.get_owner_name(piece_cord),
+                        )
+                        .as_str(),
+                    );
+                }
+                if col < BOARD_SIZE - 1 {
+                    board_string.push('|');
+                }
+            }
+            board_string.push_str("]\n");
+        }
+        write!(f, "{}", board_string)
     }
 }
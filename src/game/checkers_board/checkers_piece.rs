@@ -1,30 +1,18 @@
-use super::super::utils::EMPTY_POS;
+use super::super::side::Side;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CheckerPiece {
     pub kinged: bool,
-    pub owner: String,
-    pub direction: i32,
+    pub owner: Side,
     pub loc: (usize, usize),
 }
 
 impl CheckerPiece {
-    fn init() -> CheckerPiece {
+    pub fn init(owner: Side, loc: (usize, usize)) -> CheckerPiece {
         let piece = CheckerPiece {
             kinged: false,
-            owner: EMPTY_POS.to_string(),
-            direction: 0,                  // no direction
-            loc: (usize::MAX, usize::MAX), // default location not on board
-        };
-        return piece;
-    }
-
-    pub fn init_with_loc(loc: (usize, usize)) -> CheckerPiece {
-        let piece = CheckerPiece {
-            kinged: false,
-            owner: EMPTY_POS.to_string(),
-            direction: 0, // no direction
-            loc: loc,
+            owner,
+            loc,
         };
         return piece;
     }
@@ -0,0 +1,108 @@
+use super::{squares, CheckersBoard};
+use crate::game::side::Side;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    InvalidTurn(String),     // side to move that isn't W or B
+    InvalidField(String),    // piece list that doesn't start with W or B
+    InvalidSquare(String),   // square that isn't numbered 1-32
+    DuplicateSquare(String), // square listed more than once
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::InvalidTurn(turn) => write!(f, "\"{}\" is not a side to move", turn),
+            FenError::InvalidField(field) => write!(f, "\"{}\" is not a list of pieces", field),
+            FenError::InvalidSquare(square) => write!(f, "\"{}\" is not a square", square),
+            FenError::DuplicateSquare(square) => {
+                write!(f, "\"{}\" is listed more than once", square)
+            }
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// FEN letter of a side, black is dark and white is light
+fn side_letter(side: Side) -> char {
+    match side {
+        Side::Dark => 'B',
+        Side::Light => 'W',
+    }
+}
+
+fn parse_side(letter: &str) -> Option<Side> {
+    match letter {
+        "B" => Some(Side::Dark),
+        "W" => Some(Side::Light),
+        _ => None,
+    }
+}
+
+/// Parse a square number, or a range of square numbers such as `1-12`
+fn parse_squares(squares: &str) -> Option<Vec<u32>> {
+    let (first, last) = squares.split_once('-').unwrap_or((squares, squares));
+    let first = first.parse::<u32>().ok()?;
+    let last = last.parse::<u32>().ok()?;
+    if first < 1 || last > 32 || first > last {
+        return None;
+    }
+    return Some((first..=last).collect());
+}
+
+impl CheckersBoard {
+    /// Set up a board from a FEN string such as `W:W21,22,K30:B1,2,K9`, giving the side to move
+    /// followed by white's (light) and black's (dark) squares with kings marked by a K. Ranges
+    /// of squares like `B1-12` are also accepted.
+    ///
+    /// **Returns** - the board along with the side to move
+    pub fn init_from_fen(fen: &str) -> Result<(CheckersBoard, Side), FenError> {
+        let fen = fen.trim().trim_matches('"').trim_end_matches('.');
+        let mut fields = fen.split(':');
+        let turn_field = fields.next().unwrap_or_default().trim();
+        let turn =
+            parse_side(turn_field).ok_or_else(|| FenError::InvalidTurn(turn_field.to_string()))?;
+
+        let mut board = CheckersBoard::init();
+        for field in fields.map(str::trim).filter(|field| !field.is_empty()) {
+            let (letter, pieces) = field.split_at_checked(1).unwrap_or((field, ""));
+            let side =
+                parse_side(letter).ok_or_else(|| FenError::InvalidField(field.to_string()))?;
+            for piece in pieces.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                let kinged = piece.starts_with('K');
+                let numbers = parse_squares(piece.trim_start_matches('K'))
+                    .ok_or_else(|| FenError::InvalidSquare(piece.to_string()))?;
+                for number in numbers {
+                    let square = number - 1;
+                    if (board.dark | board.light) & (1 << square) != 0 {
+                        return Err(FenError::DuplicateSquare(piece.to_string()));
+                    }
+                    board.add_square(square, side, kinged);
+                }
+            }
+        }
+        return Ok((board, turn));
+    }
+
+    /// FEN string of the board with `turn` to move, listing white's (light) pieces first
+    pub fn get_fen(&self, turn: Side) -> String {
+        let mut fen = String::from(side_letter(turn));
+        for side in [Side::Light, Side::Dark] {
+            let pieces: Vec<String> = squares(self.get_side_mask(side))
+                .map(|square| {
+                    let king = if self.kings & (1 << square) != 0 {
+                        "K"
+                    } else {
+                        ""
+                    };
+                    format!("{}{}", king, square + 1)
+                })
+                .collect();
+            fen.push_str(&format!(":{}{}", side_letter(side), pieces.join(",")));
+        }
+        return fen;
+    }
+}
@@ -0,0 +1,55 @@
+use super::super::side::Side;
+
+/// Number of playable squares, one key is needed per piece kind per square
+const SQUARE_COUNT: usize = 32;
+
+/// Random keys for a dark man, dark king, light man and light king on each square
+const PIECE_KEYS: [[u64; SQUARE_COUNT]; 4] = generate_piece_keys();
+
+/// Key mixed in when light is the side to move
+const LIGHT_TO_MOVE_KEY: u64 = splitmix64(0x005E_ED0F_C4EC_4E25).1;
+
+/// Step a splitmix64 generator, giving the next state and the random number it produced
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return (state, z ^ (z >> 31));
+}
+
+const fn generate_piece_keys() -> [[u64; SQUARE_COUNT]; 4] {
+    let mut keys = [[0; SQUARE_COUNT]; 4];
+    let mut state = 0x00C0_FFEE_D00D_F00D;
+    let mut kind = 0;
+    while kind < 4 {
+        let mut square = 0;
+        while square < SQUARE_COUNT {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys[kind][square] = key;
+            square += 1;
+        }
+        kind += 1;
+    }
+    return keys;
+}
+
+/// Key for a piece on a playable square, XOR it into a hash to add or remove the piece
+pub fn piece_key(square: u32, owner: Side, kinged: bool) -> u64 {
+    let kind = match (owner, kinged) {
+        (Side::Dark, false) => 0,
+        (Side::Dark, true) => 1,
+        (Side::Light, false) => 2,
+        (Side::Light, true) => 3,
+    };
+    return PIECE_KEYS[kind][square as usize];
+}
+
+/// Key for the side to move
+pub fn turn_key(turn: Side) -> u64 {
+    match turn {
+        Side::Dark => 0,
+        Side::Light => LIGHT_TO_MOVE_KEY,
+    }
+}
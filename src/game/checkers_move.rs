@@ -0,0 +1,56 @@
+use std::fmt;
+
+/// A complete move for a single piece, including every jump of a multi-jump chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub origin: (usize, usize),
+    pub landings: Vec<(usize, usize)>, // every square the piece lands on, in order
+    pub captures: Vec<(usize, usize)>, // pieces jumped, in the same order as `landings`
+    pub promotes: bool,
+}
+
+impl Move {
+    /// Create a move from the squares a piece visits. Captures and promotion are filled in
+    /// when the move is matched against the legal moves of a position.
+    pub fn init(origin: (usize, usize), landings: Vec<(usize, usize)>) -> Move {
+        Move {
+            origin,
+            landings,
+            captures: vec![],
+            promotes: false,
+        }
+    }
+
+    /// Final location of the moving piece
+    pub fn destination(&self) -> (usize, usize) {
+        return *self.landings.last().unwrap_or(&self.origin);
+    }
+
+    /// Every square the piece visits, starting with its origin
+    pub fn path(&self) -> Vec<(usize, usize)> {
+        let mut path = vec![self.origin];
+        path.extend(self.landings.iter());
+        return path;
+    }
+
+    pub fn is_capture(&self) -> bool {
+        return !self.captures.is_empty();
+    }
+
+    /// Check if two moves take the same piece along the same squares
+    pub fn same_path(&self, other: &Move) -> bool {
+        return self.origin == other.origin && self.landings == other.landings;
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = if self.is_capture() { " x " } else { " - " };
+        let squares: Vec<String> = self
+            .path()
+            .iter()
+            .map(|(x, y)| format!("({}, {})", x, y))
+            .collect();
+        write!(f, "{}", squares.join(separator))
+    }
+}
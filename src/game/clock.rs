@@ -0,0 +1,129 @@
+use super::side::Side;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How much time the players get for their moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    Fischer { base: Duration, increment: Duration }, // the increment is added after every move
+    Bronstein { base: Duration, delay: Duration }, // time used on a move is given back, up to the delay
+    PerMove { time: Duration }, // every move has to be made in the time, unused time isn't kept
+}
+
+impl TimeControl {
+    /// Time on each clock when the game starts
+    pub fn get_base(&self) -> Duration {
+        match self {
+            TimeControl::Fischer { base, .. } | TimeControl::Bronstein { base, .. } => *base,
+            TimeControl::PerMove { time } => *time,
+        }
+    }
+}
+
+/// Clocks of both sides of a game, only the clock of the side to move runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clock {
+    time_control: TimeControl,
+    dark: Duration, // time dark has left, not counting a running turn
+    light: Duration,
+    running: Option<(Side, Instant)>, // side whose clock is running and when it started
+}
+
+impl Clock {
+    pub fn init(time_control: TimeControl) -> Clock {
+        let base = time_control.get_base();
+        return Clock {
+            time_control,
+            dark: base,
+            light: base,
+            running: None,
+        };
+    }
+
+    pub fn get_time_control(&self) -> TimeControl {
+        return self.time_control;
+    }
+
+    pub fn get_running(&self) -> Option<Side> {
+        return self.running.map(|(side, _)| side);
+    }
+
+    /// Time `side` has left at `now`
+    pub fn get_remaining(&self, side: Side, now: Instant) -> Duration {
+        let left = match side {
+            Side::Dark => self.dark,
+            Side::Light => self.light,
+        };
+        match self.running {
+            Some((running, since)) if running == side => {
+                left.saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => left,
+        }
+    }
+
+    /// **Returns** - the side whose clock is running if its time has run out at `now`
+    pub fn get_flagged(&self, now: Instant) -> Option<Side> {
+        let side = self.get_running()?;
+        return self.get_remaining(side, now).is_zero().then_some(side);
+    }
+
+    /// Put both clocks back to the starting time, with neither running
+    pub fn reset(&mut self) {
+        *self = Clock::init(self.time_control);
+    }
+
+    /// Run the clock of `side`, stopping the running clock without the time control adding
+    /// any time to it
+    pub fn start(&mut self, side: Side, now: Instant) {
+        self.stop(now);
+        self.running = Some((side, now));
+    }
+
+    pub fn stop(&mut self, now: Instant) {
+        if let Some(side) = self.get_running() {
+            let left = self.get_remaining(side, now);
+            self.set_remaining(side, left);
+            self.running = None;
+        }
+    }
+
+    /// End the turn of the side whose clock is running, adding time to its clock as the time
+    /// control says, and run the other side's clock
+    pub fn complete_turn(&mut self, now: Instant) {
+        let Some((side, since)) = self.running else {
+            return;
+        };
+        let used = now.saturating_duration_since(since);
+        let left = self.get_remaining(side, now);
+        let left = match self.time_control {
+            TimeControl::Fischer { increment, .. } => left + increment,
+            TimeControl::Bronstein { delay, .. } => left + used.min(delay),
+            TimeControl::PerMove { time } => time,
+        };
+        self.set_remaining(side, left);
+        self.running = Some((side.opponent(), now));
+    }
+
+    fn set_remaining(&mut self, side: Side, left: Duration) {
+        match side {
+            Side::Dark => self.dark = left,
+            Side::Light => self.light = left,
+        }
+    }
+}
+
+/// Time control in the form it is given to `create_game`, such as "fischer 300000 5000"
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeControl::Fischer { base, increment } => {
+                write!(f, "fischer {} {}", base.as_millis(), increment.as_millis())
+            }
+            TimeControl::Bronstein { base, delay } => {
+                write!(f, "bronstein {} {}", base.as_millis(), delay.as_millis())
+            }
+            TimeControl::PerMove { time } => write!(f, "per_move {}", time.as_millis()),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use super::side::Side;
+use std::fmt;
+
+/// Why a game ended without a winner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    Repetition, // the same position occurred too many times
+    MoveRule,   // too many moves without a capture or a man being moved
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameResult {
+    Ongoing,
+    Win(Side),
+    WinOnTime(Side), // the other side ran out of time
+    Draw(DrawReason),
+}
+
+/// Draw rules a game is played under. Federations differ on the move rule, e.g. 40 or 25 moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawRules {
+    pub repetitions: u32, // occurrences of the same position (with the same player to move) that draw, 0 disables
+    pub move_limit: u32, // moves by each player without a capture or man move that draw, 0 disables
+}
+
+impl DrawRules {
+    pub fn init() -> DrawRules {
+        DrawRules {
+            repetitions: 3,
+            move_limit: 40,
+        }
+    }
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawReason::Repetition => write!(f, "repetition"),
+            DrawReason::MoveRule => write!(f, "move rule"),
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::Ongoing => write!(f, "ongoing"),
+            GameResult::Win(side) => write!(f, "{} is the winner", side),
+            GameResult::WinOnTime(side) => write!(f, "{} is the winner on time", side),
+            GameResult::Draw(reason) => write!(f, "draw by {}", reason),
+        }
+    }
+}
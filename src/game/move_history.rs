@@ -0,0 +1,15 @@
+use super::checkers_board::CheckerPiece;
+use super::checkers_move::Move;
+use super::clock::Clock;
+use super::game_result::GameResult;
+
+/// Everything needed to take back a move that was made
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub played: Move,
+    pub moved_piece: CheckerPiece, // the moving piece as it was before the move
+    pub captured: Vec<CheckerPiece>, // pieces removed by the move
+    pub quiet_moves: u32,
+    pub result: GameResult,
+    pub clock: Option<Clock>, // stopped clocks as they were when the move was made
+}
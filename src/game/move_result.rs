@@ -0,0 +1,48 @@
+use super::checkers_move::Move;
+use super::side::Side;
+use std::error::Error;
+use std::fmt;
+
+/// Result of successfully making a move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveOutcome {
+    pub played: Move, // the legal move that was made, with captures and promotion filled in
+    pub next_turn: Side,
+    pub completed: bool,
+}
+
+/// Reasons a submitted move can be rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    GameCompleted,
+    OutOfBounds((usize, usize)),
+    NotYourTurn,
+    NotYourPiece((usize, usize)),
+    CaptureMandatory,
+    IncompleteCapture,
+    IllegalMove,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::GameCompleted => write!(f, "the game is already completed"),
+            MoveError::OutOfBounds(cord) => {
+                write!(f, "({}, {}) is not on the board", cord.0, cord.1)
+            }
+            MoveError::NotYourTurn => write!(f, "it is not your turn"),
+            MoveError::NotYourPiece(cord) => {
+                write!(f, "you don't have a piece at ({}, {})", cord.0, cord.1)
+            }
+            MoveError::CaptureMandatory => {
+                write!(f, "a jump is available and must be taken")
+            }
+            MoveError::IncompleteCapture => {
+                write!(f, "the jump can continue and every jump must be taken")
+            }
+            MoveError::IllegalMove => write!(f, "the piece can't move there"),
+        }
+    }
+}
+
+impl Error for MoveError {}
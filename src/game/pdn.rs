@@ -0,0 +1,397 @@
+use super::checkers_board::FenError;
+use super::checkers_move::Move;
+use super::game_result::GameResult;
+use super::move_result::MoveError;
+use super::side::Side;
+use super::square::Square;
+use std::error::Error;
+use std::fmt;
+
+/// Columns movetext is wrapped at when a game is exported
+const LINE_WIDTH: usize = 80;
+/// Result tokens that end a game's movetext. The first score is black's (dark), the side that
+/// moves first, and the second is white's (light).
+const RESULT_TOKENS: [&str; 7] = ["1-0", "0-1", "1/2-1/2", "2-0", "0-2", "1-1", "*"];
+
+/// Standard 1-32 number of the playable square at a location
+fn cord_to_number(cord: (usize, usize)) -> u32 {
+    return Square::init_from_cord(cord).map_or(0, |square| square.get_number());
+}
+
+/// Result token for the outcome of a game
+fn result_token(result: &GameResult) -> &'static str {
+    match result {
+        GameResult::Ongoing => "*",
+        GameResult::Win(Side::Dark) | GameResult::WinOnTime(Side::Dark) => "1-0",
+        GameResult::Win(Side::Light) | GameResult::WinOnTime(Side::Light) => "0-1",
+        GameResult::Draw(_) => "1/2-1/2",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PdnError {
+    NoGame,
+    UnterminatedTag,
+    UnterminatedComment,
+    UnbalancedVariation,
+    InvalidMove(String),           // token that isn't move notation
+    UnknownMove(String),           // no legal move matches the notation
+    AmbiguousMove(String),         // more than one legal move matches the notation
+    MoveFailed(String, MoveError), // the move was rejected when replayed
+    InvalidFen(FenError),          // the FEN tag isn't a valid position
+}
+
+impl fmt::Display for PdnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PdnError::NoGame => write!(f, "no game found"),
+            PdnError::UnterminatedTag => write!(f, "tag is missing its closing bracket"),
+            PdnError::UnterminatedComment => write!(f, "comment is missing its closing brace"),
+            PdnError::UnbalancedVariation => write!(f, "variation parentheses don't match"),
+            PdnError::InvalidMove(token) => write!(f, "\"{}\" is not a move", token),
+            PdnError::UnknownMove(notation) => write!(f, "{} is not a legal move", notation),
+            PdnError::AmbiguousMove(notation) => {
+                write!(f, "{} matches more than one legal move", notation)
+            }
+            PdnError::MoveFailed(notation, error) => write!(f, "{} failed, {}", notation, error),
+            PdnError::InvalidFen(error) => write!(f, "FEN tag is invalid, {}", error),
+        }
+    }
+}
+
+impl Error for PdnError {}
+
+/// A move written in standard notation, such as `11-15` or `23x14`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdnMove {
+    pub squares: Vec<u32>, // square numbers visited, a multi-jump may list only both ends
+    pub capture: bool,
+    pub comment: Option<String>,  // comment following the move
+    pub variations: Vec<PdnLine>, // alternatives to this move
+}
+
+impl PdnMove {
+    pub fn from_move(mv: &Move) -> PdnMove {
+        return PdnMove {
+            squares: mv.path().into_iter().map(cord_to_number).collect(),
+            capture: mv.is_capture(),
+            comment: None,
+            variations: vec![],
+        };
+    }
+
+    /// Parse move notation, ignoring any `!` or `?` annotation
+    fn parse(token: &str) -> Result<PdnMove, PdnError> {
+        let notation = token.trim_end_matches(['!', '?']);
+        let squares: Option<Vec<u32>> = notation
+            .split(['-', 'x'])
+            .map(|square| square.parse::<u32>().ok().filter(|n| (1..=32).contains(n)))
+            .collect();
+        match squares {
+            Some(squares) if squares.len() >= 2 => Ok(PdnMove {
+                squares,
+                capture: notation.contains('x'),
+                comment: None,
+                variations: vec![],
+            }),
+            _ => Err(PdnError::InvalidMove(token.to_string())),
+        }
+    }
+
+    /// Check if the notation describes a move. A multi-jump written with only its first and
+    /// last squares matches any chain between them.
+    pub fn matches(&self, mv: &Move) -> bool {
+        if self.capture != mv.is_capture() {
+            return false;
+        }
+        let path: Vec<u32> = mv.path().into_iter().map(cord_to_number).collect();
+        if self.squares == path {
+            return true;
+        }
+        return self.capture
+            && self.squares.len() == 2
+            && self.squares.first() == path.first()
+            && self.squares.last() == path.last();
+    }
+}
+
+impl fmt::Display for PdnMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = if self.capture { "x" } else { "-" };
+        let squares: Vec<String> = self.squares.iter().map(|n| n.to_string()).collect();
+        write!(f, "{}", squares.join(separator))
+    }
+}
+
+/// A sequence of moves, either a game's main line or a variation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PdnLine {
+    pub comment: Option<String>, // comment before the first move
+    pub moves: Vec<PdnMove>,
+}
+
+impl PdnLine {
+    /// Movetext tokens for the line, numbering moves from `first_ply`
+    fn get_tokens(&self, first_ply: usize) -> Vec<String> {
+        let mut tokens = vec![];
+        if let Some(comment) = &self.comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        let mut needs_number = true;
+        for (index, pdn_move) in self.moves.iter().enumerate() {
+            let ply = first_ply + index;
+            if ply.is_multiple_of(2) {
+                tokens.push(format!("{}.", ply / 2 + 1));
+            } else if needs_number {
+                tokens.push(format!("{}...", ply / 2 + 1));
+            }
+            tokens.push(pdn_move.to_string());
+            needs_number = false;
+            if let Some(comment) = &pdn_move.comment {
+                tokens.push(format!("{{{}}}", comment));
+                needs_number = true;
+            }
+            for variation in &pdn_move.variations {
+                let mut variation_tokens = variation.get_tokens(ply);
+                if let Some(first) = variation_tokens.first_mut() {
+                    first.insert(0, '(');
+                }
+                if let Some(last) = variation_tokens.last_mut() {
+                    last.push(')');
+                }
+                tokens.append(&mut variation_tokens);
+                needs_number = true;
+            }
+        }
+        return tokens;
+    }
+}
+
+/// A game record in Portable Draughts Notation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdnGame {
+    pub tags: Vec<(String, String)>,
+    pub mainline: PdnLine,
+    pub result: String,
+}
+
+impl PdnGame {
+    /// Record of a game played through `Checkers`
+    pub fn init(black: &str, white: &str, moves: &[Move], result: &GameResult) -> PdnGame {
+        let result = result_token(result).to_string();
+        return PdnGame {
+            tags: vec![
+                (String::from("Black"), black.to_string()),
+                (String::from("White"), white.to_string()),
+                (String::from("Result"), result.clone()),
+            ],
+            mainline: PdnLine {
+                comment: None,
+                moves: moves.iter().map(PdnMove::from_move).collect(),
+            },
+            result,
+        };
+    }
+
+    /// Parse the first game in PDN text
+    pub fn parse(text: &str) -> Result<PdnGame, PdnError> {
+        return PdnGame::parse_all(text)?
+            .into_iter()
+            .next()
+            .ok_or(PdnError::NoGame);
+    }
+
+    /// Parse every game in PDN text, such as a database file
+    pub fn parse_all(text: &str) -> Result<Vec<PdnGame>, PdnError> {
+        let tokens = tokenize(text)?;
+        let mut games = vec![];
+        let mut index = 0;
+        while index < tokens.len() {
+            let mut tags = vec![];
+            while let Some(Token::Tag(name, value)) = tokens.get(index) {
+                tags.push((name.clone(), value.clone()));
+                index += 1;
+            }
+            let mut result = None;
+            let mainline = parse_line(&tokens, &mut index, &mut result, false)?;
+            games.push(PdnGame {
+                tags,
+                mainline,
+                result: result.unwrap_or_else(|| String::from("*")),
+            });
+        }
+        if games.is_empty() {
+            return Err(PdnError::NoGame);
+        }
+        return Ok(games);
+    }
+
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        return self
+            .tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str());
+    }
+}
+
+impl fmt::Display for PdnGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        writeln!(f)?;
+        // a game set up with light to move starts on light's half of move 1
+        let first_ply = match self.get_tag("FEN") {
+            Some(fen) if fen.trim_start().starts_with('W') => 1,
+            _ => 0,
+        };
+        let mut tokens = self.mainline.get_tokens(first_ply);
+        tokens.push(self.result.clone());
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + token.len() + 1 > LINE_WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Word(String), // move number, move or result
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PdnError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '[' => {
+                // [Name "value"], the value may escape quotes and backslashes
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ']') {
+                    name.push(c);
+                }
+                let mut value = String::new();
+                let mut in_quotes = false;
+                loop {
+                    match chars.next() {
+                        None => return Err(PdnError::UnterminatedTag),
+                        Some('"') => in_quotes = !in_quotes,
+                        Some('\\') if in_quotes => value.extend(chars.next()),
+                        Some(']') if !in_quotes => break,
+                        Some(c) if in_quotes => value.push(c),
+                        Some(_) => {}
+                    }
+                }
+                tokens.push(Token::Tag(name, value));
+            }
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(PdnError::UnterminatedComment),
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                    }
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            c => {
+                let mut word = String::from(c);
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || "[]{}()".contains(*next) {
+                        break;
+                    }
+                    word.extend(chars.next());
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    return Ok(tokens);
+}
+
+/// Check if a word is a numeric annotation glyph such as `$1`
+fn is_nag(word: &str) -> bool {
+    return word
+        .strip_prefix('$')
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()));
+}
+
+/// Parse moves until the end of a variation, or for the main line until its result, the tags of
+/// the next game or the end of the text. Numeric annotation glyphs are skipped, as is a result
+/// inside a variation. A variation given before the line's first move is an alternative to that
+/// move.
+fn parse_line(
+    tokens: &[Token],
+    index: &mut usize,
+    result: &mut Option<String>,
+    variation: bool,
+) -> Result<PdnLine, PdnError> {
+    let mut line = PdnLine::default();
+    let mut leading_variations = vec![]; // variations before the first move
+    while let Some(token) = tokens.get(*index) {
+        *index += 1;
+        match token {
+            Token::Tag(_, _) if !variation => {
+                // the next game starts without this one giving a result
+                *index -= 1;
+                return Ok(line);
+            }
+            Token::Comment(comment) => match line.moves.last_mut() {
+                Some(last) => last.comment = Some(comment.clone()),
+                None => line.comment = Some(comment.clone()),
+            },
+            Token::VariationStart => {
+                let alternative = parse_line(tokens, index, result, true)?;
+                match line.moves.last_mut() {
+                    Some(last) => last.variations.push(alternative),
+                    None => leading_variations.push(alternative),
+                }
+            }
+            Token::VariationEnd if variation => return Ok(line),
+            Token::Word(word) if RESULT_TOKENS.contains(&word.as_str()) => {
+                // a variation may end with the result it leads to, which isn't the game's
+                if !variation {
+                    *result = Some(word.clone());
+                    return Ok(line);
+                }
+            }
+            Token::Word(word) if is_nag(word) => {}
+            Token::Word(word) => {
+                // drop a move number such as `12.` or `12...` from the front of the move
+                let notation = word.trim_start_matches(|c: char| c.is_ascii_digit());
+                let notation = match notation.strip_prefix('.') {
+                    Some(rest) => rest.trim_start_matches('.'),
+                    None => word.as_str(),
+                };
+                if !notation.is_empty() {
+                    let mut pdn_move = PdnMove::parse(notation)?;
+                    pdn_move.variations.append(&mut leading_variations);
+                    line.moves.push(pdn_move);
+                }
+            }
+            Token::Tag(_, _) | Token::VariationEnd => return Err(PdnError::UnbalancedVariation),
+        }
+    }
+    if variation {
+        return Err(PdnError::UnbalancedVariation);
+    }
+    return Ok(line);
+}
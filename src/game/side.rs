@@ -0,0 +1,36 @@
+use serde::Serialize;
+use std::fmt;
+
+/// The two sides of a checkers game. Dark starts on rows 0-2 and always moves first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Light,
+    Dark,
+}
+
+impl Side {
+    pub fn opponent(&self) -> Side {
+        match self {
+            Side::Light => Side::Dark,
+            Side::Dark => Side::Light,
+        }
+    }
+
+    /// Row direction men of this side move in
+    pub fn direction(&self) -> i32 {
+        match self {
+            Side::Light => -1,
+            Side::Dark => 1,
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Light => write!(f, "light"),
+            Side::Dark => write!(f, "dark"),
+        }
+    }
+}
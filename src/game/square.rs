@@ -0,0 +1,79 @@
+use super::BOARD_SIZE;
+use std::fmt;
+
+/// Number of playable dark squares on the board
+const SQUARE_COUNT: u32 = 32;
+
+/// One of the 32 playable dark squares, numbered the standard way from 1 on dark's back rank
+/// to 32 on light's. Square 1 is row 0, column 1 and algebraic b8, while square 29 is row 7,
+/// column 0 and algebraic a1, so light's back rank is rank 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square {
+    number: u32,
+}
+
+impl Square {
+    /// **Returns** - None if `number` isn't between 1 and 32
+    pub fn init(number: u32) -> Option<Square> {
+        if !(1..=SQUARE_COUNT).contains(&number) {
+            return None;
+        }
+        return Some(Square { number });
+    }
+
+    /// **Returns** - None if the location is off the board or on a light square
+    pub fn init_from_cord(cord: (usize, usize)) -> Option<Square> {
+        let (row, col) = cord;
+        if row >= BOARD_SIZE || col >= BOARD_SIZE || (row + col).is_multiple_of(2) {
+            return None;
+        }
+        return Square::init((row * 4 + col / 2 + 1) as u32);
+    }
+
+    /// Square from algebraic notation such as `c3`, files a-h and ranks 1-8
+    ///
+    /// **Returns** - None if the notation isn't a square or names a light square
+    pub fn init_from_algebraic(notation: &str) -> Option<Square> {
+        let mut chars = notation.chars();
+        let file = chars.next()?.to_ascii_lowercase();
+        let rank = chars.as_str().parse::<usize>().ok()?;
+        if !('a'..='h').contains(&file) || !(1..=BOARD_SIZE).contains(&rank) {
+            return None;
+        }
+        return Square::init_from_cord((BOARD_SIZE - rank, file as usize - 'a' as usize));
+    }
+
+    /// Square from either its number or algebraic notation
+    pub fn parse(notation: &str) -> Option<Square> {
+        let notation = notation.trim();
+        if let Ok(number) = notation.parse::<u32>() {
+            return Square::init(number);
+        }
+        return Square::init_from_algebraic(notation);
+    }
+
+    pub fn get_number(&self) -> u32 {
+        return self.number;
+    }
+
+    /// Row and column of the square
+    pub fn get_cord(&self) -> (usize, usize) {
+        let index = (self.number - 1) as usize;
+        let row = index / 4;
+        // even rows start with a light square
+        let col = index % 4 * 2 + (row + 1) % 2;
+        return (row, col);
+    }
+
+    /// Algebraic notation of the square such as `c3`
+    pub fn get_algebraic(&self) -> String {
+        let (row, col) = self.get_cord();
+        return format!("{}{}", (b'a' + col as u8) as char, BOARD_SIZE - row);
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.number)
+    }
+}
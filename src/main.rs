@@ -1,203 +1,39 @@
+#![allow(clippy::needless_return)]
+
+mod engine;
 mod game;
+mod protocol;
+mod server;
 
-use crate::game::Checkers;
-use crate::game::EMPTY_POS;
-use std::net::TcpListener;
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex, MutexGuard};
-use std::thread::spawn;
-use tungstenite::accept;
-use tungstenite::protocol::Message;
-use tungstenite::WebSocket;
+use crate::server::Server;
+use std::sync::Arc;
 
 fn main() {
-    // Setup the checker's game
-    let option_checkers = game::Checkers::init();
-    let checkers_mutex = Arc::new(Mutex::new(option_checkers));
-
-    let player_count_shared: u8 = 0;
-    let player_count_arc = Arc::new(Mutex::new(player_count_shared));
-    let player_name_list: Vec<String> = Vec::new();
-    let player_name_list_arc = Arc::new(Mutex::new(player_name_list));
-
     // Start checkers websocket server
     println!("Starting websocket server on port 9001!");
-    let server = TcpListener::bind("127.0.0.1:9001").unwrap();
-
-    // Listen for player websocket clients
-    for stream in server.incoming() {
-        let checkers_arc = Arc::clone(&checkers_mutex);
-        let player_count_mutex = Arc::clone(&player_count_arc);
-        let player_name_list_mutex = Arc::clone(&player_name_list_arc);
-        spawn(move || {
-            let mut websocket = accept(stream.unwrap()).unwrap();
-            // Send greeting message to player client
-            let send_name_msg =
-                String::from("Welcome! Send \"help\" to see all available commands.");
-            websocket
-                .write_message(Message::Text(send_name_msg))
-                .unwrap();
-
-            let mut player_name: Option<String> = None;
-
-            // Server thread starts listening for websocket messages
-            loop {
-                let msg = websocket.read_message().unwrap();
-                if !msg.is_text() {
-                    println!("Received non-text websocket, cannot proceed");
-                    continue;
-                }
-                let mut player_count = player_count_mutex.lock().unwrap();
-                let mut checkers = &mut checkers_arc.lock().unwrap();
-                // Pre-game
-                let msg_string = msg.into_text().unwrap();
-
-                match msg_string.as_str() {
-                    m if m.contains("help") => {
-                        let arst = [
-                            "\"donger\" -- Status check msg. Outputs \"turn:<name>\\nboard:<game_board>\".",
-                            "\"set_player_name <name>\" -- Register to play a game. Outputs \"player:<name>\".",
-                            "\"move_piece <target x> <target y> <destination x> <destination y>\" -- Attempt to move a piece from a target location to a destination location. Outputs \"move_piece: success..\".",
-                        ];
-                        websocket
-                            .write_message(Message::Text(arst.join("\n")))
-                            .unwrap();
-                    }
-                    m if m.contains("donger") => {
-                        if checkers.is_completed() {
-                            game_completed(&mut websocket, checkers);
-                        }
-                        send_board(&mut websocket, checkers);
-                    }
-                    m if m.contains("set_player_name") => {
-                        println!("set_player_name: command={}", msg_string);
-                        if *player_count > 2 {
-                            println!("set_player_name: already max players registered");
-                            return;
-                        }
-                        if checkers.is_completed() {
-                            game_completed(&mut websocket, checkers);
-                            return;
-                        }
-
-                        // Add player name to list of gamers
-                        let mut msg_list = msg_string.split_whitespace();
-                        let mut player_name_list = player_name_list_mutex.lock().unwrap();
-                        let sent_player_name = msg_list.nth(1).unwrap().to_string();
-                        player_name = Some(sent_player_name.clone());
-                        player_name_list.push(sent_player_name);
-                        // Increment player count
-                        *player_count += 1;
-                        // Start game if we have at least two players registered!
-                        if *player_count == 2 {
-                            start_game(&mut websocket, checkers, player_name_list.clone());
-                        }
-                        websocket
-                            .write_message(Message::Text(format!(
-                                "player:{}",
-                                player_name.as_ref().unwrap()
-                            )))
-                            .unwrap();
-                    }
-                    m if m.contains("move_piece") => {
-                        // TODO: Start the timer (on the first iteration)!
-                        // TODO: Parse player command
-                        println!("move_piece: command={}", msg_string);
-                        // move command format
-                        // move_peice 1 2 3 2
-                        let mut arst = msg_string.split_whitespace();
-                        let c_x = arst.nth(1).unwrap().parse::<usize>().unwrap();
-                        let c_y = arst.next().unwrap().parse::<usize>().unwrap();
-                        let d_x = arst.next().unwrap().parse::<usize>().unwrap();
-                        let d_y = arst.next().unwrap().parse::<usize>().unwrap();
-                        let success = checkers.move_piece(
-                            player_name.clone().unwrap(),
-                            (c_x, c_y),
-                            (d_x, d_y),
-                        );
-                        if success {
-                            websocket
-                                .write_message(Message::Text(format!(
-                                    "move_piece:\"successfully moved piece to ({}, {})\"",
-                                    d_x, d_y
-                                )))
-                                .unwrap();
-                        } else {
-                            websocket
-                                .write_message(Message::Text(format!(
-                                    "move_piece:\"failed to movf piece to ({}, {})\"",
-                                    d_x, d_y
-                                )))
-                                .unwrap();
-                        }
-                        if checkers.is_completed() {
-                            game_completed(&mut websocket, checkers);
-                        }
-                        send_board(&mut websocket, checkers);
-                    }
-                    _ => {
-                        println!("default: received unknown command={}", msg_string.trim());
-                        websocket
-                            .write_message(Message::Text(String::from(
-                                "Unknown command. Send \"help\" to see all available commands.",
-                            )))
-                            .unwrap();
-                    }
-                }
-                checkers.print_board();
-            }
-        });
+    let server = Arc::new(Server::init());
+    if let Err(e) = server.run("127.0.0.1:9001") {
+        println!("Failed to start websocket server, {}", e);
     }
 }
 
-fn game_completed(websocket: &mut WebSocket<TcpStream>, checkers: &mut MutexGuard<Checkers>) {
-    websocket
-        .write_message(Message::Text(format!(
-            "status: game completed, {} is the winner!",
-            checkers.get_winner()
-        )))
-        .unwrap();
-}
-
-fn start_game(
-    websocket: &mut WebSocket<TcpStream>,
-    checkers: &mut MutexGuard<Checkers>,
-    player_name_list: Vec<String>,
-) {
-    let start = String::from("Game Started");
-    websocket.write_message(Message::Text(start)).unwrap();
-
-    let option_checkers = checkers.setup_players(
-        player_name_list.get(0).unwrap().clone(),
-        player_name_list.get(1).unwrap().clone(),
-    );
-    if option_checkers.is_none() {
-        println!("Failed to initialize checkers board.");
-        websocket.write_message(Message::Text(String::from(
-            "SERVER ERROR: Please notify dumb admin that checker board failed to setup!",
-        )));
-        return;
-    }
-
-    send_board(websocket, checkers);
-}
-
-fn send_board(websocket: &mut WebSocket<TcpStream>, checkers: &mut MutexGuard<Checkers>) {
-    // Use mutex to get the current player's turn
-    let current_player_turn = checkers.get_turn();
-    let board_state_string = checkers.get_board().get_board_as_string();
-
-    websocket
-        .write_message(Message::Text(format!("turn:{}", current_player_turn)))
-        .unwrap();
-    websocket
-        .write_message(Message::Text(format!("board:{}", board_state_string)))
-        .unwrap();
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::game::{self, Checkers};
+    use crate::engine::{
+        self, Engine, EvaluationWeights, Position, SearchLimits, StopSignal, WIN_SCORE,
+    };
+    use crate::game::{
+        self, Checkers, CheckersBoard, Clock, DrawReason, DrawRules, FenError, GameResult, Move,
+        MoveError, PdnError, PdnGame, Side, Square, TimeControl,
+    };
+    use crate::protocol::{self, Command, Encoding, ErrorCode, ProtocolError, Reply, Request};
+    use crate::server::{self, Connection, Server};
+    use serde_json::{json, Value};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread::{sleep, spawn};
+    use std::time::{Duration, Instant};
+    use tungstenite::Message;
 
     fn setup() -> Checkers {
         let mut checkers = game::Checkers::init();
@@ -205,282 +41,2146 @@ mod tests {
         return checkers;
     }
 
+    /// Move sent by a text "move_piece" command with the given arguments
+    fn parse_move(args: &[&str]) -> Option<Move> {
+        match Command::parse_text(&format!("move_piece {}", args.join(" "))) {
+            Ok(Command::MovePiece { path }) => protocol::path_to_move(&path),
+            _ => None,
+        }
+    }
+
+    fn parse_square(args: &[&str]) -> Option<Square> {
+        match Command::parse_text(&format!("square {}", args.join(" "))) {
+            Ok(Command::Square { square }) => square.get_square(),
+            _ => None,
+        }
+    }
+
+    fn parse_search_limits(args: &[&str]) -> SearchLimits {
+        match Command::parse_text(&format!("best_move {}", args.join(" "))) {
+            Ok(Command::BestMove(Some(request))) => request.get_limits(),
+            command => panic!("expected a best_move command, got {:?}", command),
+        }
+    }
+
+    /// Send a request to the server, returning every message sent back in the connection's
+    /// encoding
+    fn send(
+        server: &Server,
+        connection: &mut Connection,
+        encoding: Encoding,
+        message: &str,
+    ) -> Vec<String> {
+        let request = Request::decode(encoding, message);
+        let replies = match &request.command {
+            Ok(command) => server.handle_command(connection, command),
+            Err(error) => vec![Reply::Response(Err(error.clone()))],
+        };
+        return replies
+            .iter()
+            .flat_map(|reply| request.encode(encoding, reply))
+            .collect();
+    }
+
+    /// Events other clients have caused in the connection's game
+    fn received(connection: &Connection, encoding: Encoding) -> Vec<String> {
+        return connection
+            .take_events()
+            .iter()
+            .flat_map(|event| protocol::encode(encoding, event, "", &None))
+            .collect();
+    }
+
+    fn send_json(server: &Server, connection: &mut Connection, request: Value) -> Vec<Value> {
+        return send(server, connection, Encoding::Json, &request.to_string())
+            .iter()
+            .map(|message| serde_json::from_str(message).unwrap())
+            .collect();
+    }
+
     #[test]
     fn test_init() {
-        let mut checkers = game::Checkers::init();
-        assert_eq!(checkers.get_player1(), "empty");
-        assert_eq!(checkers.get_player2(), "empty");
-        assert_eq!(checkers.get_turn(), "empty");
+        let checkers = game::Checkers::init();
+        assert_eq!(checkers.get_player1(), None);
+        assert_eq!(checkers.get_player2(), None);
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        assert!(checkers.legal_moves().is_empty());
         assert!(!checkers.is_ready_to_start());
-        assert_eq!(checkers.get_winner(), "empty");
+        assert_eq!(checkers.get_result(), GameResult::Ongoing);
     }
 
     #[test]
     fn test_with_players() {
         let mut checkers = setup();
         let option = checkers.init_with_players("test1".to_string(), "test2".to_string());
-        assert_eq!(checkers.get_player1(), "test1");
-        assert_eq!(checkers.get_player2(), "test2");
-        assert_eq!(checkers.get_turn(), "test1");
+        assert!(option.is_some());
+        assert_eq!(checkers.get_player1(), Some("test1".to_string()));
+        assert_eq!(checkers.get_player2(), Some("test2".to_string()));
+        assert_eq!(checkers.get_player(Side::Light), Some("test2".to_string()));
+        assert_eq!(checkers.get_turn(), Side::Dark);
         assert!(checkers.is_ready_to_start());
-        assert_eq!(checkers.get_winner(), "empty");
+        assert_eq!(checkers.get_result(), GameResult::Ongoing);
     }
 
     #[test]
     fn test_move_piece_wrong_starting_piece() {
         let mut checkers = setup();
-        let output = checkers.move_piece("test1".to_string(), (3, 0), (4, 1));
-        assert!(!output);
+        let output = checkers.move_piece(Side::Dark, &Move::init((3, 0), vec![(4, 1)]));
+        assert_eq!(output, Err(MoveError::NotYourPiece((3, 0))));
     }
 
     #[test]
     fn test_move_piece_illegal_cords() {
         let mut checkers = setup();
-        let output = checkers.move_piece("test1".to_string(), (1, 8), (2, 9));
-        assert!(!output);
+        let output = checkers.move_piece(Side::Dark, &Move::init((1, 8), vec![(2, 9)]));
+        assert_eq!(output, Err(MoveError::OutOfBounds((1, 8))));
     }
 
     #[test]
     fn test_move_piece_wrong_turn() {
         let mut checkers = setup();
-        let output = checkers.move_piece("test2".to_string(), (5, 6), (4, 7));
-        assert!(!output);
+        let output = checkers.move_piece(Side::Light, &Move::init((5, 6), vec![(4, 7)]));
+        assert_eq!(output, Err(MoveError::NotYourTurn));
     }
 
     #[test]
     fn test_move_piece_wrong_player() {
         let mut checkers = setup();
-        let output = checkers.move_piece("test2".to_string(), (2, 1), (3, 0));
-        assert!(!output);
+        let output = checkers.move_piece(Side::Light, &Move::init((2, 1), vec![(3, 0)]));
+        assert_eq!(output, Err(MoveError::NotYourTurn));
     }
 
     #[test]
     fn test_move_piece_no_jump() {
         let mut checkers = setup();
-        let output = checkers.move_piece("test1".to_string(), (2, 1), (3, 0));
-        assert!(output);
+        let output = checkers.move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]));
+        assert!(output.is_ok());
     }
 
     #[test]
     fn test_move_piece_with_jump_available() {
         let mut checkers = setup();
-        let mut output = checkers.move_piece("test1".to_string(), (2, 7), (3, 6));
-        assert!(output);
-        output = checkers.move_piece("test2".to_string(), (5, 4), (4, 5));
-        assert!(output);
-        output = checkers.move_piece("test1".to_string(), (2, 1), (3, 0));
-        assert!(!output);
+        let mut output = checkers.move_piece(Side::Dark, &Move::init((2, 7), vec![(3, 6)]));
+        assert!(output.is_ok());
+        output = checkers.move_piece(Side::Light, &Move::init((5, 4), vec![(4, 5)]));
+        assert!(output.is_ok());
+        output = checkers.move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]));
+        assert_eq!(output, Err(MoveError::CaptureMandatory));
     }
 
     #[test]
     fn test_move_piece_jump() {
         let mut checkers = setup();
-        let mut output = checkers.move_piece("test1".to_string(), (2, 7), (3, 6));
-        assert!(output);
-        output = checkers.move_piece("test2".to_string(), (5, 4), (4, 5));
-        assert!(output);
-        output = checkers.move_piece("test1".to_string(), (3, 6), (5, 4));
-        assert!(output);
+        let mut output = checkers.move_piece(Side::Dark, &Move::init((2, 7), vec![(3, 6)]));
+        assert!(output.is_ok());
+        output = checkers.move_piece(Side::Light, &Move::init((5, 4), vec![(4, 5)]));
+        assert!(output.is_ok());
+        output = checkers.move_piece(Side::Dark, &Move::init((3, 6), vec![(5, 4)]));
+        let outcome = output.unwrap();
+        assert_eq!(outcome.played.captures, vec![(4, 5)]);
+        assert_eq!(outcome.next_turn, Side::Light);
+        assert!(!outcome.completed);
     }
 
     #[test]
     fn test_move_piece_double_jump() {
         let mut checkers = setup();
-        let board = &mut checkers.get_board().positions;
-        let mut piece2 = board.get(5).unwrap().get(0).unwrap().clone();
-        piece2.occupant.loc = (3, 6);
-        board[3][6] = piece2.clone();
-        let mut empty = board.get(3).unwrap().get(0).unwrap().clone();
-        empty.occupant.loc = (6, 5);
-        board[6][5] = empty.clone();
+        // a light piece on 16 and none on 27 lets dark double jump from 11
+        assert!(checkers.load_fen("B:W16,21-26,28-32:B1-12").is_ok());
 
-        let mut output = checkers.move_piece("test1".to_string(), (2, 5), (4, 7));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test1");
-        output = checkers.move_piece("test1".to_string(), (4, 7), (6, 5));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test2");
+        // stopping part way through the chain isn't a complete move
+        let mut output = checkers.move_piece(Side::Dark, &Move::init((2, 5), vec![(4, 7)]));
+        assert_eq!(output, Err(MoveError::IncompleteCapture));
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        output = checkers.move_piece(Side::Dark, &Move::init((2, 5), vec![(4, 7), (6, 5)]));
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Light);
+        assert_eq!(
+            checkers.get_board().get_player_pieces(Side::Light).len(),
+            10
+        );
     }
 
     #[test]
     fn test_move_piece_kinged() {
         let mut checkers = setup();
-        let board = &mut checkers.get_board().positions;
-        let mut piece2 = board.get(5).unwrap().get(0).unwrap().clone();
-        piece2.occupant.loc = (1, 6);
-        board[1][6] = piece2.clone();
-        let mut empty = board.get(3).unwrap().get(0).unwrap().clone();
-        empty.occupant.loc = (0, 7);
-        empty.owner = "test1".to_string();
-        board[0][7] = empty.clone();
-        empty.occupant.loc = (0, 5);
-        board[0][5] = empty.clone();
-        empty.occupant.loc = (2, 7);
-        board[2][7] = empty.clone();
-        empty.occupant.loc = (3, 6);
-        board[3][6] = empty.clone();
-        empty.occupant.loc = (4, 7);
-        board[4][7] = empty.clone();
-        empty.occupant.loc = (5, 6);
-        board[5][6] = empty.clone();
-        empty.occupant.loc = (6, 7);
-        board[6][7] = empty.clone();
-        empty.occupant.loc = (7, 6);
-        board[7][6] = empty.clone();
+        // a light piece on 8 one step away from being kinged on 4
+        assert!(checkers
+            .load_fen("B:W8,21-23,25-27,29-31:B1,2,5-7,9-11")
+            .is_ok());
         checkers.print_board();
 
-        let mut output = checkers.move_piece("test1".to_string(), (2, 5), (3, 4));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test2");
-        output = checkers.move_piece("test2".to_string(), (1, 6), (0, 7));
+        let mut output = checkers.move_piece(Side::Dark, &Move::init((2, 5), vec![(3, 4)]));
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Light);
+        output = checkers.move_piece(Side::Light, &Move::init((1, 6), vec![(0, 7)]));
         checkers.print_board();
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test1");
-        assert!(
-            checkers
-                .get_board()
-                .positions
-                .get(0)
-                .unwrap()
-                .get(7)
-                .unwrap()
-                .occupant
-                .kinged
-        );
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        assert!(checkers.get_board().get_piece((0, 7)).unwrap().kinged);
     }
 
     #[test]
     fn test_move_piece_king_jump_normal() {
         let mut checkers = setup();
-        let board = &mut checkers.get_board().positions;
-        let mut piece2 = board.get(5).unwrap().get(0).unwrap().clone();
-        piece2.occupant.loc = (1, 6);
-        board[1][6] = piece2.clone();
-        let mut empty = board.get(3).unwrap().get(0).unwrap().clone();
-        empty.occupant.loc = (0, 7);
-        empty.owner = "test1".to_string();
-        board[0][7] = empty.clone();
-        empty.occupant.loc = (0, 5);
-        board[0][5] = empty.clone();
-        empty.occupant.loc = (2, 7);
-        board[2][7] = empty.clone();
-        empty.occupant.loc = (3, 6);
-        board[3][6] = empty.clone();
-        empty.occupant.loc = (4, 7);
-        board[4][7] = empty.clone();
-        empty.occupant.loc = (5, 6);
-        board[5][6] = empty.clone();
-        empty.occupant.loc = (6, 7);
-        board[6][7] = empty.clone();
-        empty.occupant.loc = (7, 6);
-        board[7][6] = empty.clone();
-        checkers.print_board();
+        // a light king on 18 with a dark piece in front of it on 14
+        assert!(checkers.load_fen("W:WK18:B1,14").is_ok());
+
+        let output = checkers.move_piece(Side::Light, &Move::init((4, 3), vec![(2, 1)]));
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        let board = checkers.get_board();
+        assert!(board.get_piece((4, 3)).is_none());
+        assert!(board.get_piece((3, 2)).is_none());
+        let king = board.get_piece((2, 1)).unwrap();
+        assert_eq!(king.owner, Side::Light);
+        assert!(king.kinged);
+        assert_eq!(board.get_player_pieces(Side::Dark).len(), 1);
+    }
 
-        let mut output = checkers.move_piece("test1".to_string(), (2, 5), (3, 4));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test2");
-        output = checkers.move_piece("test2".to_string(), (1, 6), (0, 7));
+    #[test]
+    fn test_move_piece_king_jump_backwards() {
+        let mut checkers = setup();
+        assert!(checkers
+            .load_fen("B:W8,21-23,25-27,29-31:B1,2,5-7,9,11")
+            .is_ok());
+
+        let mut output = checkers.move_piece(Side::Dark, &Move::init((2, 5), vec![(3, 4)]));
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Light);
+        output = checkers.move_piece(Side::Light, &Move::init((1, 6), vec![(0, 5)]));
+        checkers.print_board();
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        output = checkers.move_piece(Side::Dark, &Move::init((3, 4), vec![(4, 5)]));
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Light);
+        output = checkers.move_piece(Side::Light, &Move::init((0, 5), vec![(2, 3)]));
         checkers.print_board();
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test1");
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Dark);
+    }
+
+    #[test]
+    fn test_move_piece_into_winning() {
+        let mut checkers = setup();
+        assert!(checkers.load_fen("B:W9:B5").is_ok());
+
+        let output = checkers.move_piece(Side::Dark, &Move::init((1, 0), vec![(3, 2)]));
+        assert!(output.is_ok());
+        assert_eq!(checkers.get_turn(), Side::Light);
+        assert!(checkers.is_completed());
+        assert_eq!(checkers.get_result(), GameResult::Win(Side::Dark));
+    }
+
+    #[test]
+    fn test_legal_moves_opening() {
+        let checkers = setup();
+        let moves = checkers.legal_moves();
+        assert_eq!(moves.len(), 7);
+        assert!(moves
+            .iter()
+            .all(|mv| mv.landings.len() == 1 && mv.origin.0 == 2 && !mv.is_capture()));
+        assert!(moves.iter().any(|mv| mv.path() == vec![(2, 1), (3, 0)]));
+    }
+
+    #[test]
+    fn test_legal_moves_capture_mandatory() {
+        let mut checkers = setup();
+        assert!(checkers
+            .move_piece(Side::Dark, &Move::init((2, 7), vec![(3, 6)]))
+            .is_ok());
+        assert!(checkers
+            .move_piece(Side::Light, &Move::init((5, 4), vec![(4, 5)]))
+            .is_ok());
+        let moves = checkers.legal_moves();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].path(), vec![(3, 6), (5, 4)]);
+        assert_eq!(moves[0].captures, vec![(4, 5)]);
+    }
+
+    #[test]
+    fn test_legal_moves_double_jump_chain() {
+        let mut checkers = setup();
+        // a light piece on 16 and none on 27 lets dark double jump from 11
+        assert!(checkers.load_fen("B:W16,21-26,28-32:B1-12").is_ok());
+
+        let moves = checkers.legal_moves();
+        assert_eq!(moves.len(), 2);
+        assert!(moves
+            .iter()
+            .any(|mv| mv.path() == vec![(2, 5), (4, 7), (6, 5)]
+                && mv.captures == vec![(3, 6), (5, 6)]));
+        assert!(moves.iter().any(|mv| mv.path() == vec![(2, 7), (4, 5)]));
+    }
+
+    #[test]
+    fn test_legal_moves_game_not_started() {
+        let checkers = game::Checkers::init();
+        assert!(checkers.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_move_piece_illegal_destination() {
+        let mut checkers = setup();
+        let output = checkers.move_piece(Side::Dark, &Move::init((2, 1), vec![(4, 1)]));
+        assert_eq!(output, Err(MoveError::IllegalMove));
+        let output = checkers.move_piece(Side::Dark, &Move::init((1, 0), vec![(2, 1)]));
+        assert_eq!(output, Err(MoveError::IllegalMove));
+        assert_eq!(checkers.get_turn(), Side::Dark);
+    }
+
+    #[test]
+    fn test_move_piece_game_completed() {
+        let mut checkers = setup();
+        let board = checkers.get_board();
+        for piece in board.get_player_pieces(Side::Light) {
+            board.remove_piece(piece.loc);
+        }
+        let mut output = checkers.move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]));
+        assert!(output.unwrap().completed);
+        output = checkers.move_piece(Side::Light, &Move::init((5, 0), vec![(4, 1)]));
+        assert_eq!(output, Err(MoveError::GameCompleted));
+    }
+
+    #[test]
+    fn test_move_piece_blocked_player_loses() {
+        let mut checkers = setup();
+        // light's only piece is stuck behind two dark pieces
+        assert!(checkers.load_fen("B:W29:B9,22,25").is_ok());
+
+        let output = checkers.move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]));
+        assert!(output.unwrap().completed);
+        assert!(checkers.legal_moves().is_empty());
+        assert_eq!(checkers.get_result(), GameResult::Win(Side::Dark));
+    }
+
+    /// Leave each player with a single king that can shuffle back and forth
+    fn setup_kings_only(checkers: &mut Checkers) {
+        assert!(checkers.load_fen("B:WK27:BK14").is_ok());
+    }
+
+    /// A player moving a piece from one location to another
+    type Ply = (Side, (usize, usize), (usize, usize));
+
+    /// Plies moving both kings away from and back to their starting squares
+    const KING_SHUFFLE: [Ply; 4] = [
+        (Side::Dark, (3, 2), (2, 3)),
+        (Side::Light, (6, 5), (7, 6)),
+        (Side::Dark, (2, 3), (3, 2)),
+        (Side::Light, (7, 6), (6, 5)),
+    ];
+
+    #[test]
+    fn test_draw_by_repetition() {
+        let mut checkers = setup();
+        setup_kings_only(&mut checkers);
+
+        // the starting position occurs for the third time after ply 8
+        for ply in 0..8 {
+            let (player, from, to) = KING_SHUFFLE[ply % 4];
+            assert_eq!(checkers.get_result(), GameResult::Ongoing);
+            assert!(checkers
+                .move_piece(player, &Move::init(from, vec![to]))
+                .is_ok());
+        }
+        assert_eq!(
+            checkers.get_result(),
+            GameResult::Draw(DrawReason::Repetition)
+        );
+        assert!(checkers.is_completed());
+    }
+
+    #[test]
+    fn test_draw_by_move_rule() {
+        let mut checkers = Checkers::init_with_draw_rules(DrawRules {
+            repetitions: 0,
+            move_limit: 2,
+        });
+        checkers.init_with_players("test1".to_string(), "test2".to_string());
+        setup_kings_only(&mut checkers);
+
+        for (player, from, to) in KING_SHUFFLE {
+            assert_eq!(checkers.get_result(), GameResult::Ongoing);
+            assert!(checkers
+                .move_piece(player, &Move::init(from, vec![to]))
+                .is_ok());
+        }
+        assert_eq!(
+            checkers.get_result(),
+            GameResult::Draw(DrawReason::MoveRule)
+        );
+    }
+
+    #[test]
+    fn test_man_move_resets_move_rule() {
+        let mut checkers = Checkers::init_with_draw_rules(DrawRules {
+            repetitions: 0,
+            move_limit: 1,
+        });
+        checkers.init_with_players("test1".to_string(), "test2".to_string());
+        assert!(checkers
+            .move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]))
+            .is_ok());
+        assert!(checkers
+            .move_piece(Side::Light, &Move::init((5, 0), vec![(4, 1)]))
+            .is_ok());
+        assert_eq!(checkers.get_result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn test_undo_move() {
+        let mut checkers = setup();
+        assert_eq!(checkers.undo_move(), None);
+        let mv = Move::init((2, 1), vec![(3, 0)]);
+        assert!(checkers.move_piece(Side::Dark, &mv).is_ok());
+        assert_eq!(checkers.get_move_history().len(), 1);
+
+        let undone = checkers.undo_move().unwrap();
+        assert!(undone.same_path(&mv));
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        assert!(checkers.get_move_history().is_empty());
+        let board = checkers.get_board();
+        assert_eq!(board.get_piece((2, 1)).map(|p| p.owner), Some(Side::Dark));
+        assert_eq!(board.get_piece((3, 0)), None);
+    }
+
+    #[test]
+    fn test_undo_capture_restores_pieces() {
+        let mut checkers = setup();
+        for (player, from, to) in [
+            (Side::Dark, (2, 7), (3, 6)),
+            (Side::Light, (5, 4), (4, 5)),
+            (Side::Dark, (3, 6), (5, 4)),
+        ] {
+            assert!(checkers
+                .move_piece(player, &Move::init(from, vec![to]))
+                .is_ok());
+        }
+        assert_eq!(
+            checkers.get_board().get_player_pieces(Side::Light).len(),
+            11
+        );
+
+        checkers.undo_move();
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        assert_eq!(
+            checkers.get_board().get_player_pieces(Side::Light).len(),
+            12
+        );
+        assert_eq!(
+            checkers.get_board().get_piece((4, 5)).map(|p| p.owner),
+            Some(Side::Light)
+        );
+        assert_eq!(
+            checkers.get_board().get_piece((3, 6)).map(|p| p.owner),
+            Some(Side::Dark)
+        );
+        assert_eq!(checkers.legal_moves().len(), 1);
+    }
+
+    #[test]
+    fn test_undo_kinged_piece_and_result() {
+        let mut checkers = setup();
+        assert!(checkers.load_fen("B:W9:B5,25").is_ok());
+
+        // dark jumps light's only piece, completing the game
         assert!(
             checkers
-                .get_board()
-                .positions
-                .get(0)
+                .move_piece(Side::Dark, &Move::init((1, 0), vec![(3, 2)]))
                 .unwrap()
-                .get(7)
+                .completed
+        );
+        checkers.undo_move();
+        assert_eq!(checkers.get_result(), GameResult::Ongoing);
+        assert_eq!(
+            checkers.get_board().get_piece((2, 1)).map(|p| p.owner),
+            Some(Side::Light)
+        );
+
+        // kinging is taken back along with the move
+        assert!(checkers.load_fen("B:W20:B5,25").is_ok());
+        assert!(
+            checkers
+                .move_piece(Side::Dark, &Move::init((6, 1), vec![(7, 0)]))
                 .unwrap()
-                .occupant
-                .kinged
+                .played
+                .promotes
         );
-        output = checkers.move_piece("test1".to_string(), (2, 1), (3, 0));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test2");
-        output = checkers.move_piece("test2".to_string(), (0, 7), (1, 6));
-        checkers.print_board();
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test1");
+        checkers.undo_move();
+        assert!(!checkers.get_board().get_piece((6, 1)).unwrap().kinged);
     }
 
     #[test]
-    fn test_move_piece_king_jump_backwards() {
+    fn test_redo_move() {
         let mut checkers = setup();
-        let board = &mut checkers.get_board().positions;
-        let mut piece2 = board.get(5).unwrap().get(0).unwrap().clone();
-        piece2.occupant.loc = (1, 6);
-        board[1][6] = piece2.clone();
-        let mut empty = board.get(3).unwrap().get(0).unwrap().clone();
-        empty.occupant.loc = (0, 7);
-        empty.owner = "test1".to_string();
-        board[0][7] = empty.clone();
-        empty.occupant.loc = (0, 5);
-        board[0][5] = empty.clone();
-        empty.occupant.loc = (2, 7);
-        board[2][7] = empty.clone();
-        empty.occupant.loc = (3, 6);
-        board[3][6] = empty.clone();
-        empty.occupant.loc = (4, 7);
-        board[4][7] = empty.clone();
-        empty.occupant.loc = (5, 6);
-        board[5][6] = empty.clone();
-        empty.occupant.loc = (6, 7);
-        board[6][7] = empty.clone();
-        empty.occupant.loc = (7, 6);
-        board[7][6] = empty.clone();
-        empty.occupant.loc = (2, 3);
-        board[2][3] = empty.clone();
-
-        let mut output = checkers.move_piece("test1".to_string(), (2, 5), (3, 4));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test2");
-        output = checkers.move_piece("test2".to_string(), (1, 6), (0, 5));
-        checkers.print_board();
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test1");
-        output = checkers.move_piece("test1".to_string(), (3, 4), (4, 5));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test2");
-        output = checkers.move_piece("test2".to_string(), (0, 5), (2, 3));
-        checkers.print_board();
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test1");
+        let first = Move::init((2, 1), vec![(3, 0)]);
+        let second = Move::init((5, 0), vec![(4, 1)]);
+        assert!(checkers.move_piece(Side::Dark, &first).is_ok());
+        assert!(checkers.move_piece(Side::Light, &second).is_ok());
+        checkers.undo_move();
+        checkers.undo_move();
+        assert_eq!(checkers.get_turn(), Side::Dark);
+
+        assert!(checkers.redo_move().unwrap().same_path(&first));
+        assert!(checkers.redo_move().unwrap().same_path(&second));
+        assert_eq!(checkers.redo_move(), None);
+        assert_eq!(checkers.get_turn(), Side::Dark);
+        assert_eq!(checkers.get_move_history().len(), 2);
+
+        // making a new move throws away anything left to redo
+        checkers.undo_move();
+        assert!(checkers
+            .move_piece(Side::Light, &Move::init((5, 2), vec![(4, 3)]))
+            .is_ok());
+        assert_eq!(checkers.redo_move(), None);
     }
 
     #[test]
-    fn test_move_piece_into_winning() {
+    fn test_player_names_are_not_identifiers() {
+        let mut checkers = game::Checkers::init();
+        assert!(checkers
+            .init_with_players("empty".to_string(), "empty".to_string())
+            .is_some());
+        assert_eq!(checkers.legal_moves().len(), 7);
+        assert!(checkers
+            .move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]))
+            .is_ok());
+        assert_eq!(checkers.get_turn(), Side::Light);
+        assert_eq!(checkers.get_player(Side::Light), Some("empty".to_string()));
+    }
+
+    #[test]
+    fn test_board_pieces_round_trip() {
+        let mut checkers = setup();
+        let board = checkers.get_board();
+        assert_eq!(board.get_player_pieces(Side::Dark).len(), 12);
+        assert_eq!(board.get_piece((1, 1)), None);
+        assert_eq!(board.get_piece((8, 0)), None);
+
+        let (mut board, _) = CheckersBoard::init_from_fen("B:WK18,21-32:B1-12").unwrap();
+        let piece = board.get_piece((4, 3)).unwrap();
+        assert_eq!(piece.owner, Side::Light);
+        assert!(piece.kinged);
+        board.remove_piece((4, 3));
+        assert_eq!(board.get_piece((4, 3)), None);
+        assert!(board.is_board_pieces_ready());
+        assert_eq!(board, *checkers.get_board());
+    }
+
+    #[test]
+    fn test_square_conversions() {
+        for (number, cord, algebraic) in [
+            (1, (0, 1), "b8"),
+            (4, (0, 7), "h8"),
+            (5, (1, 0), "a7"),
+            (14, (3, 2), "c5"),
+            (29, (7, 0), "a1"),
+            (32, (7, 6), "g1"),
+        ] {
+            let square = Square::init(number).unwrap();
+            assert_eq!(square.get_cord(), cord);
+            assert_eq!(square.get_algebraic(), algebraic);
+            assert_eq!(Square::init_from_cord(cord), Some(square));
+            assert_eq!(Square::init_from_algebraic(algebraic), Some(square));
+            assert_eq!(Square::parse(&number.to_string()), Some(square));
+        }
+        for number in 1..=32 {
+            let square = Square::init(number).unwrap();
+            assert_eq!(Square::init_from_cord(square.get_cord()), Some(square));
+            assert_eq!(Square::parse(&square.get_algebraic()), Some(square));
+        }
+        assert_eq!(Square::init(0), None);
+        assert_eq!(Square::init(33), None);
+        assert_eq!(Square::init_from_cord((0, 0)), None);
+        assert_eq!(Square::init_from_cord((8, 1)), None);
+        assert_eq!(Square::parse("a8"), None);
+        assert_eq!(Square::parse("i1"), None);
+        assert_eq!(Square::parse("b9"), None);
+
+        assert_eq!(parse_square(&["3", "4"]), Square::init(15));
+        assert_eq!(parse_square(&["e5"]), Square::init(15));
+        assert_eq!(parse_square(&["15"]), Square::init(15));
+        assert_eq!(parse_square(&["3", "3"]), None);
+    }
+
+    #[test]
+    fn test_parse_move_notations() {
+        let expected = Move::init((2, 5), vec![(4, 7), (6, 5)]);
+        for args in [
+            vec!["2", "5", "4", "7", "6", "5"],
+            vec!["11x20x27"],
+            vec!["11", "x", "20", "x", "27"],
+            vec!["f6", "h4", "f2"],
+            vec!["f6x", "h4xf2"],
+        ] {
+            assert_eq!(parse_move(&args), Some(expected.clone()));
+        }
+        assert_eq!(parse_move(&["2", "5", "4"]), None);
+        assert_eq!(parse_move(&["11"]), None);
+        assert_eq!(parse_move(&["11-33"]), None);
+        assert_eq!(parse_move(&["a2", "b3"]), None);
+        // two numbers alone can't be a row and column move, so they are square numbers
+        assert_eq!(
+            parse_move(&["11", "15"]),
+            Some(Move::init((2, 5), vec![(3, 4)]))
+        );
+        assert_eq!(parse_move(&["11", "33"]), None);
+
         let mut checkers = setup();
-        let board = &mut checkers.get_board().positions;
-        for row_index in 0..board.len() {
-            for col_index in 0..board[row_index].len() {
-                let mut piece = board
-                    .get(row_index)
-                    .unwrap()
-                    .get(col_index)
-                    .unwrap()
-                    .clone();
-                if !piece.blocked {
-                    piece.occupant.owner = "empty".to_string();
-                    board[row_index][col_index] = piece;
-                }
+        let mv = parse_move(&["12-16"]).unwrap();
+        assert!(checkers.move_piece(Side::Dark, &mv).is_ok());
+        let mv = parse_move(&["c3", "d4"]).unwrap();
+        assert!(checkers.move_piece(Side::Light, &mv).is_ok());
+        assert_eq!(
+            checkers.get_move_history(),
+            vec![
+                Move::init((2, 7), vec![(3, 6)]),
+                Move::init((5, 2), vec![(4, 3)])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fen() {
+        let mut checkers = setup();
+        assert_eq!(
+            checkers.get_fen(),
+            "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12"
+        );
+        assert!(checkers.load_fen("W:W21,22,K30:B1,2,K9").is_ok());
+        assert_eq!(checkers.get_turn(), Side::Light);
+        assert_eq!(checkers.get_fen(), "W:W21,22,K30:B1,2,K9");
+        let king = checkers.get_board().get_piece((2, 1)).unwrap();
+        assert_eq!(king.owner, Side::Dark);
+        assert!(king.kinged);
+        assert!(!checkers.get_board().get_piece((5, 2)).unwrap().kinged);
+        assert!(checkers.get_move_history().is_empty());
+
+        // a position where the side to move is blocked is already lost
+        assert!(checkers.load_fen("W:W29:B22,25").is_ok());
+        assert_eq!(checkers.get_result(), GameResult::Win(Side::Dark));
+
+        assert_eq!(
+            checkers.load_fen("X:W21:B1"),
+            Err(FenError::InvalidTurn(String::from("X")))
+        );
+        assert_eq!(
+            checkers.load_fen("W:W21,33:B1"),
+            Err(FenError::InvalidSquare(String::from("33")))
+        );
+        assert_eq!(
+            checkers.load_fen("W:W21:B21"),
+            Err(FenError::DuplicateSquare(String::from("21")))
+        );
+        assert_eq!(
+            checkers.load_fen("W:Q21"),
+            Err(FenError::InvalidField(String::from("Q21")))
+        );
+    }
+
+    #[test]
+    fn test_best_move_start_position() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let result = Engine::init()
+            .best_move(&position, &SearchLimits::init_with_depth(4))
+            .unwrap();
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.pv.len(), 4);
+        assert!(result.best_move.same_path(&result.pv[0]));
+        assert!(result.nodes > 0);
+
+        // the principal variation is a line of legal moves
+        let mut line = position;
+        for mv in &result.pv {
+            assert!(line.legal_moves().contains(mv));
+            line = line.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_best_move_finds_win() {
+        let mut checkers = setup();
+        assert!(checkers.load_fen("B:W9:B5,23").is_ok());
+
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let result = Engine::init()
+            .best_move(&position, &SearchLimits::init_with_depth(6))
+            .unwrap();
+        assert_eq!(result.best_move.path(), vec![(1, 0), (3, 2)]);
+        assert_eq!(result.score, WIN_SCORE - 1);
+        // a forced win stops the search early
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn test_best_move_no_legal_moves() {
+        let mut checkers = setup();
+        assert!(checkers.load_fen("W:W29:B22,25").is_ok());
+
+        let position = Position::init(*checkers.get_board(), Side::Light);
+        assert_eq!(
+            Engine::init().best_move(&position, &SearchLimits::init()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_evaluate_start_position() {
+        let mut checkers = setup();
+        let weights = EvaluationWeights::init();
+        // both sides are equal apart from having the move
+        assert_eq!(
+            engine::evaluate(checkers.get_board(), Side::Dark),
+            weights.to_move
+        );
+        assert_eq!(
+            engine::evaluate(checkers.get_board(), Side::Light),
+            weights.to_move
+        );
+    }
+
+    #[test]
+    fn test_evaluate_material() {
+        let mut checkers = setup();
+        checkers.get_board().remove_piece((5, 0));
+        assert!(engine::evaluate(checkers.get_board(), Side::Dark) > 0);
+        assert!(engine::evaluate(checkers.get_board(), Side::Light) < 0);
+
+        assert!(checkers.load_fen("B:WK22:B9").is_ok());
+        let material_only = EvaluationWeights {
+            man: 100,
+            king: 160,
+            advancement: 0,
+            back_rank: 0,
+            center: 0,
+            mobility: 0,
+            to_move: 0,
+        };
+        assert_eq!(
+            engine::evaluate_with_weights(checkers.get_board(), Side::Light, &material_only),
+            60
+        );
+    }
+
+    #[test]
+    fn test_position_hash_transpositions() {
+        let mut first = setup();
+        let mut second = setup();
+        let start = first.get_position_hash();
+        for (player, from, to) in [
+            (Side::Dark, (2, 1), (3, 0)),
+            (Side::Light, (5, 6), (4, 7)),
+            (Side::Dark, (2, 7), (3, 6)),
+            (Side::Light, (5, 2), (4, 3)),
+        ] {
+            assert!(first
+                .move_piece(player, &Move::init(from, vec![to]))
+                .is_ok());
+        }
+        for (player, from, to) in [
+            (Side::Dark, (2, 7), (3, 6)),
+            (Side::Light, (5, 2), (4, 3)),
+            (Side::Dark, (2, 1), (3, 0)),
+        ] {
+            assert!(second
+                .move_piece(player, &Move::init(from, vec![to]))
+                .is_ok());
+            assert_ne!(first.get_position_hash(), second.get_position_hash());
+        }
+        assert!(second
+            .move_piece(Side::Light, &Move::init((5, 6), vec![(4, 7)]))
+            .is_ok());
+        assert_eq!(first.get_position_hash(), second.get_position_hash());
+
+        // the hash is kept up to date when moves are taken back
+        while first.undo_move().is_some() {}
+        assert_eq!(first.get_position_hash(), start);
+    }
+
+    #[test]
+    fn test_position_hash_side_to_move() {
+        let mut checkers = setup();
+        let board = *checkers.get_board();
+        assert_ne!(
+            board.get_position_hash(Side::Dark),
+            board.get_position_hash(Side::Light)
+        );
+        assert!(checkers.load_fen("B:W21-32:B1-12,K14").is_ok());
+        checkers.get_board().remove_piece((3, 2));
+        assert_eq!(*checkers.get_board(), board);
+    }
+
+    #[test]
+    fn test_engine_reuses_transposition_table() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let limits = SearchLimits::init_with_depth(6);
+        let mut engine = Engine::init();
+        let first = engine.best_move(&position, &limits).unwrap();
+        let second = engine.best_move(&position, &limits).unwrap();
+        assert!(second.nodes < first.nodes);
+        assert_eq!(second.score, first.score);
+
+        // a fresh table has to search everything again
+        engine.set_hash_size(1);
+        let third = engine.best_move(&position, &limits).unwrap();
+        assert!(third.nodes > second.nodes);
+    }
+
+    #[test]
+    fn test_quiescence_resolves_captures() {
+        let mut checkers = setup();
+        // dark's capture lands next to a light piece that takes it straight back
+        assert!(checkers.load_fen("B:W18,27,32:B1,14").is_ok());
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let limits = SearchLimits::init_with_depth(1);
+
+        let mut engine = Engine::init();
+        engine.options.quiescence_depth = 0;
+        let horizon = engine.best_move(&position, &limits).unwrap();
+        engine.set_hash_size(1);
+        engine.options.quiescence_depth = 8;
+        let resolved = engine.best_move(&position, &limits).unwrap();
+        assert!(resolved.best_move.same_path(&horizon.best_move));
+        assert!(resolved.score < horizon.score - 50);
+    }
+
+    #[test]
+    fn test_quiescence_finds_loss() {
+        let mut checkers = setup();
+        // dark's only move is a capture that light takes back with dark's last piece
+        assert!(checkers.load_fen("B:W18,27,32:B14").is_ok());
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let result = Engine::init()
+            .best_move(&position, &SearchLimits::init_with_depth(1))
+            .unwrap();
+        assert_eq!(result.score, -(WIN_SCORE - 2));
+    }
+
+    #[test]
+    fn test_search_node_limit() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let mut limits = SearchLimits::init_with_depth(30);
+        limits.nodes = Some(5000);
+        let result = Engine::init().best_move(&position, &limits).unwrap();
+        assert!(result.depth < 30);
+        assert!(result.nodes < 5000 + 1024);
+        assert!(position.legal_moves().contains(&result.best_move));
+
+        // the limit counts the nodes of every thread together
+        let mut engine = Engine::init();
+        engine.options.threads = 4;
+        let result = engine.best_move(&position, &limits).unwrap();
+        assert!(result.nodes < 5000 + 4 * 1024);
+        assert!(position.legal_moves().contains(&result.best_move));
+    }
+
+    #[test]
+    fn test_search_time_limits() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let mut engine = Engine::init();
+        let mut limits = SearchLimits::init();
+        limits.depth = None;
+        limits.movetime = Some(Duration::from_millis(100));
+        let start = Instant::now();
+        assert!(engine.best_move(&position, &limits).is_some());
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        limits.movetime = None;
+        limits.time_left = Some(Duration::from_millis(600));
+        limits.increment = Duration::from_millis(10);
+        let start = Instant::now();
+        assert!(engine.best_move(&position, &limits).is_some());
+        assert!(start.elapsed() < Duration::from_millis(600));
+    }
+
+    #[test]
+    fn test_search_stopped_from_another_thread() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let stop_signal = StopSignal::init();
+        let mut engine = Engine::init_with_stop_signal(stop_signal.clone());
+        let mut limits = SearchLimits::init();
+        limits.depth = None;
+
+        let stopper = spawn(move || {
+            sleep(Duration::from_millis(100));
+            stop_signal.stop();
+        });
+        let start = Instant::now();
+        let result = engine.best_move(&position, &limits).unwrap();
+        stopper.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(position.legal_moves().contains(&result.best_move));
+    }
+
+    #[test]
+    fn test_text_commands_match_first_word() {
+        assert_eq!(
+            Command::parse_text("helpdesk"),
+            Err(ProtocolError::init(
+                ErrorCode::UnknownCommand,
+                "Unknown command. Send \"help\" to see all available commands."
+            ))
+        );
+        assert_eq!(
+            Command::parse_text("set_player_name helpdesk"),
+            Ok(Command::SetPlayerName {
+                name: String::from("helpdesk")
+            })
+        );
+        assert_eq!(Command::parse_text("  donger "), Ok(Command::Status));
+        assert_eq!(
+            Command::parse_text("set_option threads many"),
+            Err(ProtocolError::init(
+                ErrorCode::InvalidArgument,
+                "invalid option or value for threads"
+            ))
+        );
+        assert_eq!(
+            Command::parse_text("load_fen W:W21:B1"),
+            Ok(Command::LoadFen {
+                fen: String::from("W:W21:B1")
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_json_requests() {
+        let request = Request::decode(
+            Encoding::Json,
+            r#"{"version": 1, "id": "a1", "command": "move_piece", "payload": {"path": [11, "g5", [4, 7]]}}"#,
+        );
+        assert_eq!(request.id, Some(json!("a1")));
+        let Ok(Command::MovePiece { path }) = request.command else {
+            panic!("expected a move_piece command");
+        };
+        assert_eq!(
+            protocol::path_to_move(&path),
+            Some(Move::init((2, 5), vec![(3, 6), (4, 7)]))
+        );
+
+        let request = Request::decode(Encoding::Json, r#"{"version": 1, "command": "status"}"#);
+        assert_eq!(request.command, Ok(Command::Status));
+        let request = Request::decode(
+            Encoding::Json,
+            r#"{"version": 1, "command": "best_move", "payload": {"movetime": 50}}"#,
+        );
+        let Ok(Command::BestMove(Some(search))) = request.command else {
+            panic!("expected a best_move command");
+        };
+        assert_eq!(
+            search.get_limits().movetime,
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(search.get_limits().depth, None);
+        // every search limit is optional, so the payload is too
+        let request = Request::decode(Encoding::Json, r#"{"version": 1, "command": "best_move"}"#);
+        assert_eq!(request.command, Ok(Command::BestMove(None)));
+
+        for (message, code) in [
+            ("help", ErrorCode::InvalidRequest),
+            ("[1, 2]", ErrorCode::InvalidRequest),
+            (r#"{"command": "help"}"#, ErrorCode::InvalidRequest),
+            (
+                r#"{"version": 2, "command": "help"}"#,
+                ErrorCode::UnsupportedVersion,
+            ),
+            (
+                r#"{"version": 1, "command": "helpdesk"}"#,
+                ErrorCode::UnknownCommand,
+            ),
+            (
+                r#"{"version": 1, "command": "set_player_name", "payload": {}}"#,
+                ErrorCode::InvalidArgument,
+            ),
+        ] {
+            let request = Request::decode(Encoding::Json, message);
+            assert_eq!(request.command.unwrap_err().code, code, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_server_json_protocol() {
+        let server = Server::init();
+        let mut dark = Connection::init(&server);
+        let mut light = Connection::init(&server);
+
+        let mut replies = send_json(
+            &server,
+            &mut dark,
+            json!({"version": 1, "id": 1, "command": "set_player_name", "payload": {"name": "alice"}}),
+        );
+        let token = replies[0]["payload"]["token"].take();
+        assert_eq!(token.as_str().map(str::len), Some(32));
+        replies[0]["payload"]
+            .as_object_mut()
+            .unwrap()
+            .remove("token");
+        assert_eq!(
+            replies,
+            vec![json!({
+                "version": 1,
+                "id": 1,
+                "command": "set_player_name",
+                "status": "ok",
+                "payload": {"game": 1, "name": "alice", "side": "dark"}
+            })]
+        );
+
+        // a move before joining is rejected with an error code
+        let replies = send_json(
+            &server,
+            &mut light,
+            json!({"version": 1, "id": "m", "command": "move_piece", "payload": {"path": [22, 18]}}),
+        );
+        assert_eq!(replies[0]["status"], "error");
+        assert_eq!(replies[0]["id"], "m");
+        assert_eq!(replies[0]["error"]["code"], "not_playing");
+
+        let replies = send_json(
+            &server,
+            &mut light,
+            json!({"version": 1, "command": "set_player_name", "payload": {"name": "bob"}}),
+        );
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["event"], "game_started");
+        assert_eq!(
+            replies[0]["payload"],
+            json!({"dark": "alice", "light": "bob"})
+        );
+        assert_eq!(replies[1]["event"], "board");
+        assert_eq!(replies[1]["payload"]["turn"], "dark");
+        assert_eq!(
+            replies[1]["payload"]["pieces"].as_array().unwrap().len(),
+            24
+        );
+        assert_eq!(replies[2]["payload"]["side"], "light");
+
+        let replies = send_json(
+            &server,
+            &mut dark,
+            json!({"version": 1, "id": 2, "command": "move_piece", "payload": {"path": ["h6", "g5"]}}),
+        );
+        assert_eq!(replies[0]["status"], "ok");
+        assert_eq!(replies[0]["payload"]["notation"], "12-16");
+        assert_eq!(replies[1]["event"], "board");
+        assert_eq!(replies[1]["payload"]["player"], "bob");
+
+        let replies = send_json(
+            &server,
+            &mut dark,
+            json!({"version": 1, "id": 3, "command": "move_piece", "payload": {"path": [16, 20]}}),
+        );
+        assert_eq!(replies[0]["error"]["code"], "not_your_turn");
+
+        // light can't take back dark's move
+        let replies = send_json(
+            &server,
+            &mut light,
+            json!({"version": 1, "command": "undo"}),
+        );
+        assert_eq!(replies[0]["status"], "error");
+        assert_eq!(replies[0]["error"]["code"], "not_your_turn");
+
+        // dark asks to take it back and light agrees
+        let replies = send_json(&server, &mut dark, json!({"version": 1, "command": "undo"}));
+        assert_eq!(
+            replies[0]["payload"],
+            json!({"side": "dark", "offer": "undo"})
+        );
+        let events = received(&light, Encoding::Json);
+        let offered: Value = serde_json::from_str(events.last().unwrap()).unwrap();
+        assert_eq!(offered["event"], "offered");
+        let replies = send_json(
+            &server,
+            &mut light,
+            json!({"version": 1, "command": "undo"}),
+        );
+        assert_eq!(replies[0]["payload"]["path"], json!([[2, 7], [3, 6]]));
+        assert_eq!(replies.last().unwrap()["payload"]["turn"], "dark");
+        let replies = send_json(&server, &mut dark, json!({"version": 1, "command": "undo"}));
+        assert_eq!(replies[0]["error"]["code"], "no_moves");
+    }
+
+    #[test]
+    fn test_server_text_protocol() {
+        let server = Server::init();
+        let mut dark = Connection::init(&server);
+        let mut light = Connection::init(&server);
+        let replies = send(&server, &mut dark, Encoding::Text, "set_player_name alice");
+        assert_eq!(replies[..3], ["player:alice", "side:dark", "game:1"]);
+        assert!(replies[3].starts_with("token:"));
+        let replies = send(&server, &mut light, Encoding::Text, "set_player_name bob");
+        assert_eq!(replies[0], "Game Started");
+        assert_eq!(replies[1], "turn:alice");
+        assert!(replies[2].starts_with("board:[[X, (0, 1, dark), X"));
+        assert_eq!(replies[3..6], ["player:bob", "side:light", "game:1"]);
+        // only the bonus for having the move separates the sides, and dark has it
+        assert_eq!(
+            send(&server, &mut light, Encoding::Text, "evaluate"),
+            vec!["evaluation:5"]
+        );
+        // counts run off the read loop, sending their result once they end
+        assert_eq!(
+            send(&server, &mut light, Encoding::Text, "perft 3"),
+            vec!["perft:\"counting\""]
+        );
+        let mut events = vec![];
+        while events.is_empty() {
+            sleep(Duration::from_millis(10));
+            events = received(&light, Encoding::Text);
+        }
+        assert_eq!(events, vec!["perft:302"]);
+
+        let replies = send(&server, &mut dark, Encoding::Text, "move_piece 2 7 3 6");
+        assert_eq!(
+            replies[0],
+            "move_piece:\"successfully moved piece to (3, 6)\""
+        );
+        assert_eq!(replies[1], "turn:bob");
+        let replies = send(&server, &mut light, Encoding::Text, "move_piece 22-19");
+        assert_eq!(
+            replies[0],
+            "move_piece:\"failed to move piece to (4, 5), the piece can't move there\""
+        );
+        assert_eq!(
+            send(&server, &mut light, Encoding::Text, "history"),
+            vec!["history:[(2, 7) - (3, 6)]"]
+        );
+        assert_eq!(
+            send(&server, &mut light, Encoding::Text, "square 15"),
+            vec!["square:15 (3, 4) e5"]
+        );
+        assert_eq!(
+            send(&server, &mut light, Encoding::Text, "helpdesk"),
+            vec!["Unknown command. Send \"help\" to see all available commands."]
+        );
+        assert_eq!(
+            send(&server, &mut light, Encoding::Text, "stop"),
+            vec!["stop:\"stopping search\""]
+        );
+        assert_eq!(
+            send(&server, &mut light, Encoding::Text, "set_option hash 64"),
+            vec!["set_option:hash=64"]
+        );
+        assert_eq!(
+            send(
+                &server,
+                &mut light,
+                Encoding::Text,
+                "set_option hash 1000000000000"
+            ),
+            vec!["set_option:\"hash must be from 1 to 256\""]
+        );
+        assert_eq!(
+            send(
+                &server,
+                &mut light,
+                Encoding::Text,
+                "set_option threads 100000"
+            ),
+            vec!["set_option:\"threads must be from 1 to 16\""]
+        );
+    }
+
+    #[test]
+    fn test_server_search_can_be_stopped() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        send(&server, &mut alice, Encoding::Text, "create_game alice");
+        send(&server, &mut bob, Encoding::Text, "join_game 1 bob");
+        send(
+            &server,
+            &mut bob,
+            Encoding::Text,
+            "best_move movetime 60000",
+        );
+        received(&alice, Encoding::Text);
+
+        // the search runs off the read loop, so commands are still answered
+        let start = Instant::now();
+        assert_eq!(
+            send(
+                &server,
+                &mut alice,
+                Encoding::Text,
+                "best_move movetime 60000"
+            ),
+            vec!["best_move:\"searching\""]
+        );
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "best_move"),
+            vec!["best_move:\"a search is already running, stop it first\""]
+        );
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "evaluate"),
+            vec!["evaluation:5"]
+        );
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "stop"),
+            vec!["stop:\"stopping search\""]
+        );
+        let mut events = vec![];
+        while events.is_empty() {
+            sleep(Duration::from_millis(10));
+            events = received(&alice, Encoding::Text);
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(events[0].starts_with("best_move:[") && events[0].contains(" pv:"));
+
+        // stopping one client's search leaves the others running
+        assert!(bob.is_searching());
+        send(&server, &mut bob, Encoding::Text, "stop");
+
+        // every search limit is optional, so a JSON request can leave out the payload
+        let mut carol = Connection::init(&server);
+        send(&server, &mut carol, Encoding::Text, "watch 1");
+        let replies = send_json(
+            &server,
+            &mut carol,
+            json!({"version": 1, "command": "best_move"}),
+        );
+        assert_eq!(replies[0]["status"], "ok");
+        assert!(carol.is_searching());
+        send(&server, &mut carol, Encoding::Text, "stop");
+    }
+
+    #[test]
+    fn test_server_lobby() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        let mut carol = Connection::init(&server);
+        let mut dave = Connection::init(&server);
+
+        let replies = send(&server, &mut alice, Encoding::Text, "create_game alice");
+        assert_eq!(replies[..3], ["player:alice", "side:dark", "game:1"]);
+        let replies = send(&server, &mut carol, Encoding::Text, "create_game carol");
+        assert_eq!(replies[..3], ["player:carol", "side:dark", "game:2"]);
+        assert_eq!(
+            send(&server, &mut bob, Encoding::Text, "list_games"),
+            vec!["games:[1 alice - waiting, 2 carol - waiting]"]
+        );
+        let replies = send_json(
+            &server,
+            &mut dave,
+            json!({"version": 1, "command": "join_game", "payload": {"game": 3, "name": "dave"}}),
+        );
+        assert_eq!(replies[0]["error"]["code"], "no_such_game");
+        let replies = send(&server, &mut dave, Encoding::Text, "join_game 2 dave");
+        assert_eq!(replies[0], "Game Started");
+        assert_eq!(replies[1], "turn:carol");
+        assert_eq!(replies[3..6], ["player:dave", "side:light", "game:2"]);
+        let replies = send(&server, &mut bob, Encoding::Text, "join_game 2 bob");
+        assert_eq!(
+            replies,
+            vec!["join_game:\"already max players registered\""]
+        );
+        let replies = send(&server, &mut bob, Encoding::Text, "join_game 1 bob");
+        assert_eq!(replies[3..6], ["player:bob", "side:light", "game:1"]);
+        let replies = send(&server, &mut bob, Encoding::Text, "create_game bob");
+        assert_eq!(replies, vec!["create_game:\"already playing in game 1\""]);
+
+        // moves in one game leave the other untouched
+        send(&server, &mut alice, Encoding::Text, "move_piece 11-15");
+        assert_eq!(
+            send(&server, &mut carol, Encoding::Text, "history"),
+            vec!["history:[]"]
+        );
+        assert_eq!(
+            send(&server, &mut bob, Encoding::Text, "history"),
+            vec!["history:[(2, 5) - (3, 4)]"]
+        );
+        let replies = send_json(
+            &server,
+            &mut alice,
+            json!({"version": 1, "command": "list_games"}),
+        );
+        assert_eq!(
+            replies[0]["payload"]["games"][1],
+            json!({"id": 2, "dark": "carol", "light": "dave", "status": "playing", "time_control": null})
+        );
+    }
+
+    #[test]
+    fn test_server_broadcasts_events() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        let mut carol = Connection::init(&server);
+        send(&server, &mut alice, Encoding::Text, "create_game alice");
+        send(&server, &mut carol, Encoding::Text, "create_game carol");
+        send(
+            &server,
+            &mut bob,
+            Encoding::Json,
+            r#"{"version": 1, "command": "join_game", "payload": {"game": 1, "name": "bob"}}"#,
+        );
+        let events = received(&alice, Encoding::Text);
+        assert_eq!(events[..2], ["Game Started", "turn:alice"]);
+        assert!(events[2].starts_with("board:"));
+        assert!(received(&bob, Encoding::Json).is_empty());
+
+        // the opponent is sent the move and the new board, a rejected move isn't sent
+        send(&server, &mut alice, Encoding::Text, "move_piece 11-15");
+        send(&server, &mut alice, Encoding::Text, "move_piece 15-19");
+        let events: Vec<Value> = received(&bob, Encoding::Json)
+            .iter()
+            .map(|message| serde_json::from_str(message).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["event"], "move_played");
+        assert_eq!(events[0]["payload"]["notation"], "11-15");
+        assert_eq!(events[1]["event"], "board");
+        assert_eq!(events[1]["payload"]["turn"], "light");
+        assert!(received(&alice, Encoding::Text).is_empty());
+
+        // a position is only loaded once both players ask for it
+        assert_eq!(
+            send(&server, &mut bob, Encoding::Text, "load_fen W:W22:B18"),
+            vec!["offered:light load_fen W:W22:B18"]
+        );
+        assert_eq!(
+            received(&alice, Encoding::Text),
+            vec!["offered:light load_fen W:W22:B18"]
+        );
+        let replies = send(&server, &mut alice, Encoding::Text, "load_fen W:W22:B18");
+        assert_eq!(replies[0], "load_fen:W:W22:B18");
+        assert_eq!(received(&bob, Encoding::Text)[0], "load_fen:W:W22:B18");
+
+        // asking for a different change is a counter offer rather than agreeing
+        send(&server, &mut bob, Encoding::Text, "load_fen W:W22:B9");
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "load_fen W:W21:B9"),
+            vec!["offered:dark load_fen W:W21:B9"]
+        );
+        assert_eq!(
+            send(&server, &mut bob, Encoding::Text, "fen"),
+            vec!["fen:W:W22:B18"]
+        );
+
+        // the game ends when light has no pieces left
+        received(&alice, Encoding::Text);
+        send(&server, &mut bob, Encoding::Text, "move_piece 22x15");
+        let events = received(&alice, Encoding::Text);
+        assert_eq!(
+            events[0],
+            "move_piece:\"successfully moved piece to (3, 4)\""
+        );
+        assert!(events[1].starts_with("status: game completed, bob (light) is the winner"));
+        assert!(received(&carol, Encoding::Text).is_empty());
+        assert_eq!(
+            send(&server, &mut bob, Encoding::Text, "undo"),
+            vec!["undo:\"the game is already completed\""]
+        );
+
+        // before the opponent has joined there is nobody to agree, and the game starts from the
+        // position loaded
+        assert_eq!(
+            send(&server, &mut carol, Encoding::Text, "undo"),
+            vec!["undo:\"no moves to take back\""]
+        );
+        let replies = send(&server, &mut carol, Encoding::Text, "load_fen W:W22:B18");
+        assert_eq!(replies[0], "load_fen:W:W22:B18");
+        let mut dave = Connection::init(&server);
+        let replies = send(&server, &mut dave, Encoding::Text, "join_game 2 dave");
+        assert_eq!(replies[..2], ["Game Started", "turn:dave"]);
+        assert_eq!(
+            send(&server, &mut dave, Encoding::Text, "fen"),
+            vec!["fen:W:W22:B18"]
+        );
+    }
+
+    #[test]
+    fn test_server_watchers() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        let mut carol = Connection::init(&server);
+        send(&server, &mut alice, Encoding::Text, "create_game alice");
+        assert_eq!(
+            send(&server, &mut carol, Encoding::Text, "watch 2"),
+            vec!["watch:\"no game with id 2\""]
+        );
+        let replies = send(&server, &mut carol, Encoding::Text, "watch 1");
+        assert_eq!(replies[0], "watching:1 alice - waiting");
+        assert!(replies[2].starts_with("board:"));
+
+        // watchers are sent every change to the game
+        send(&server, &mut bob, Encoding::Text, "join_game 1 bob");
+        send(&server, &mut alice, Encoding::Text, "move_piece 11-15");
+        let events = received(&carol, Encoding::Text);
+        assert_eq!(events[..2], ["Game Started", "turn:alice"]);
+        assert_eq!(
+            events[3],
+            "move_piece:\"successfully moved piece to (3, 4)\""
+        );
+        assert_eq!(events[4], "turn:bob");
+
+        // but can't change it
+        let replies = send_json(
+            &server,
+            &mut carol,
+            json!({"version": 1, "command": "move_piece", "payload": {"path": [22, 18]}}),
+        );
+        assert_eq!(replies[0]["error"]["code"], "not_playing");
+        assert_eq!(
+            send(&server, &mut carol, Encoding::Text, "undo"),
+            vec!["undo:\"watchers can't change the game\""]
+        );
+        assert_eq!(
+            send(&server, &mut carol, Encoding::Text, "history"),
+            vec!["history:[(2, 5) - (3, 4)]"]
+        );
+        assert_eq!(
+            send(&server, &mut carol, Encoding::Text, "join_game 1 carol"),
+            vec!["join_game:\"already watching game 1\""]
+        );
+        // players aren't sent anything for requests from watchers
+        assert_eq!(received(&alice, Encoding::Text).len(), 3);
+        assert_eq!(received(&bob, Encoding::Text).len(), 3);
+        send(&server, &mut carol, Encoding::Text, "status");
+        assert!(received(&alice, Encoding::Text).is_empty());
+    }
+
+    #[test]
+    fn test_server_reconnect() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        let replies = send(&server, &mut alice, Encoding::Text, "create_game alice");
+        let token = replies[3].strip_prefix("token:").unwrap().to_string();
+        let replies = send(&server, &mut bob, Encoding::Text, "join_game 1 bob");
+        assert_ne!(replies[6], format!("token:{}", token));
+        send(&server, &mut alice, Encoding::Text, "move_piece 11-15");
+
+        // alice's connection drops, reconnecting on a new one takes back the dark side
+        let mut reconnected = Connection::init(&server);
+        let replies = send_json(
+            &server,
+            &mut reconnected,
+            json!({"version": 1, "command": "reconnect", "payload": {"token": "0123"}}),
+        );
+        assert_eq!(replies[0]["error"]["code"], "no_such_session");
+        let replies = send_json(
+            &server,
+            &mut reconnected,
+            json!({"version": 1, "command": "reconnect", "payload": {"token": token}}),
+        );
+        assert_eq!(
+            replies[0]["payload"],
+            json!({"game": 1, "name": "alice", "side": "dark", "token": token})
+        );
+        assert_eq!(replies[1]["event"], "board");
+        assert_eq!(replies[1]["payload"]["turn"], "light");
+        assert_eq!(
+            send(&server, &mut reconnected, Encoding::Text, "history"),
+            vec!["history:[(2, 5) - (3, 4)]"]
+        );
+
+        // the new connection is sent the game's events and the old one can't move any more
+        received(&alice, Encoding::Text);
+        send(&server, &mut bob, Encoding::Text, "move_piece 22-18");
+        assert!(received(&alice, Encoding::Text).is_empty());
+        assert_eq!(received(&reconnected, Encoding::Text).len(), 3);
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "move_piece 15x22"),
+            vec!["move_piece:\"another connection has taken over your side\""]
+        );
+        let replies = send(
+            &server,
+            &mut reconnected,
+            Encoding::Text,
+            "move_piece 15x22",
+        );
+        assert_eq!(
+            replies[0],
+            "move_piece:\"successfully moved piece to (5, 2)\""
+        );
+    }
+
+    #[test]
+    fn test_server_sessions_expire() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        let mut carol = Connection::init(&server);
+        let replies = send(&server, &mut alice, Encoding::Text, "create_game alice");
+        let abandoned = replies[3].strip_prefix("token:").unwrap().to_string();
+        assert_eq!(abandoned.len(), 32);
+        send(
+            &server,
+            &mut bob,
+            Encoding::Text,
+            "create_game bob per_move 50",
+        );
+        let replies = send(&server, &mut carol, Encoding::Text, "join_game 2 carol");
+        let finished = replies
+            .iter()
+            .find_map(|reply| reply.strip_prefix("token:"))
+            .unwrap()
+            .to_string();
+
+        // a game's sessions end with it, the game stays listed for a while to show the result
+        sleep(Duration::from_millis(80));
+        server.check_clocks();
+        server.expire_sessions(Instant::now());
+        let reconnect = format!("reconnect {}", finished);
+        assert_eq!(
+            send(
+                &server,
+                &mut Connection::init(&server),
+                Encoding::Text,
+                &reconnect
+            ),
+            vec!["reconnect:\"no session with that token\""]
+        );
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "list_games"),
+            vec!["games:[1 alice - waiting, 2 bob carol completed per_move 50]"]
+        );
+
+        // sessions time out, taking games nobody can get back to with them
+        server.expire_sessions(Instant::now() + Duration::from_secs(25 * 60 * 60));
+        let reconnect = format!("reconnect {}", abandoned);
+        assert_eq!(
+            send(
+                &server,
+                &mut Connection::init(&server),
+                Encoding::Text,
+                &reconnect
+            ),
+            vec!["reconnect:\"no session with that token\""]
+        );
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "list_games"),
+            vec!["games:[]"]
+        );
+
+        // a game that is still being used keeps its sessions however old they are
+        let start = Instant::now();
+        let mut dave = Connection::init(&server);
+        let replies = send(&server, &mut dave, Encoding::Text, "create_game dave");
+        let token = replies[3].strip_prefix("token:").unwrap().to_string();
+        sleep(Duration::from_millis(50));
+        send(&server, &mut dave, Encoding::Text, "legal_moves");
+        // a day after the session was created, but not after the game was last used
+        server
+            .expire_sessions(start + Duration::from_secs(24 * 60 * 60) + Duration::from_millis(25));
+        let replies = send(
+            &server,
+            &mut Connection::init(&server),
+            Encoding::Text,
+            &format!("reconnect {}", token),
+        );
+        assert_eq!(replies[..3], ["player:dave", "side:dark", "game:3"]);
+
+        // a finished game that is started again is kept like any other
+        let mut erin = Connection::init(&server);
+        let mut frank = Connection::init(&server);
+        send(
+            &server,
+            &mut erin,
+            Encoding::Text,
+            "create_game erin per_move 50",
+        );
+        send(&server, &mut frank, Encoding::Text, "join_game 4 frank");
+        sleep(Duration::from_millis(80));
+        server.check_clocks();
+        server.expire_sessions(Instant::now());
+        send(
+            &server,
+            &mut erin,
+            Encoding::Text,
+            "load_fen B:W21-32:B1-12",
+        );
+        send(
+            &server,
+            &mut frank,
+            Encoding::Text,
+            "load_fen B:W21-32:B1-12",
+        );
+        server.expire_sessions(Instant::now() + Duration::from_secs(11 * 60));
+        assert_eq!(
+            send(&server, &mut erin, Encoding::Text, "list_games"),
+            vec!["games:[3 dave - waiting, 4 erin frank playing per_move 50]"]
+        );
+    }
+
+    #[test]
+    fn test_server_new_game_after_game_ends() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        let mut carol = Connection::init(&server);
+        send(&server, &mut alice, Encoding::Text, "create_game alice");
+        send(
+            &server,
+            &mut bob,
+            Encoding::Text,
+            "create_game bob per_move 50",
+        );
+        send(&server, &mut carol, Encoding::Text, "join_game 2 carol");
+        assert_eq!(
+            send(&server, &mut carol, Encoding::Text, "watch 1"),
+            vec!["watch:\"already playing in game 2\""]
+        );
+
+        // players of a finished game leave it for the next one
+        sleep(Duration::from_millis(80));
+        server.check_clocks();
+        assert!(received(&carol, Encoding::Text)[0].contains("winner on time"));
+        let replies = send(&server, &mut bob, Encoding::Text, "create_game bob");
+        assert_eq!(replies[..3], ["player:bob", "side:dark", "game:3"]);
+        let replies = send(&server, &mut carol, Encoding::Text, "join_game 3 carol");
+        assert!(replies.contains(&"game:3".to_string()));
+        send(&server, &mut bob, Encoding::Text, "move_piece 11-15");
+        let events = received(&carol, Encoding::Text);
+        assert_eq!(
+            events[0],
+            "move_piece:\"successfully moved piece to (3, 4)\""
+        );
+
+        // as do players of a game the lobby no longer has
+        server.expire_sessions(Instant::now() + Duration::from_secs(25 * 60 * 60));
+        let replies = send(&server, &mut alice, Encoding::Text, "create_game alice");
+        assert_eq!(replies[..3], ["player:alice", "side:dark", "game:4"]);
+    }
+
+    #[test]
+    fn test_server_malformed_commands() {
+        let server = Server::init();
+        let mut connection = Connection::init(&server);
+        assert_eq!(
+            send(&server, &mut connection, Encoding::Text, "undo"),
+            vec!["undo:\"join or create a game first\""]
+        );
+        send(
+            &server,
+            &mut connection,
+            Encoding::Text,
+            "create_game alice",
+        );
+        let bad_move = "failed to parse move, expected row and column pairs, square numbers or algebraic squares";
+        for (message, reply) in [
+            ("move_piece", format!("move_piece:\"{}\"", bad_move)),
+            ("move_piece 2 7 3", format!("move_piece:\"{}\"", bad_move)),
+            (
+                "set_player_name",
+                String::from("set_player_name:\"missing player name\""),
+            ),
+            (
+                "join_game bob",
+                String::from("join_game:\"missing or invalid game id\""),
+            ),
+            (
+                "join_game 1",
+                String::from("join_game:\"missing player name\""),
+            ),
+            ("perft", String::from("perft:\"missing or invalid depth\"")),
+            (
+                "divide x",
+                String::from("divide:\"missing or invalid depth\""),
+            ),
+            (
+                "perft 30",
+                String::from("perft:\"depth can be at most 10\""),
+            ),
+            (
+                "watch",
+                String::from("watch:\"missing or invalid game id\""),
+            ),
+            (
+                "reconnect",
+                String::from("reconnect:\"missing session token\""),
+            ),
+        ] {
+            assert_eq!(
+                send(&server, &mut connection, Encoding::Text, message),
+                vec![reply],
+                "{}",
+                message
+            );
+        }
+
+        // the connection carries on as normal
+        let mut bob = Connection::init(&server);
+        send(&server, &mut bob, Encoding::Text, "join_game 1 bob");
+        let replies = send(&server, &mut connection, Encoding::Text, "move_piece 11-15");
+        assert_eq!(
+            replies[0],
+            "move_piece:\"successfully moved piece to (3, 4)\""
+        );
+    }
+
+    #[test]
+    fn test_server_connection_teardown() {
+        let server = Arc::new(Server::init());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let serving = Arc::clone(&server);
+        let serve_two = spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                serving.serve(stream);
+            }
+        });
+
+        // a client dropping its socket without closing the websocket ends only its connection
+        let url = format!("ws://{}{}", address, server::JSON_PATH);
+        let (mut client, _) =
+            tungstenite::client(&url, TcpStream::connect(address).unwrap()).unwrap();
+        client.read_message().unwrap();
+        client
+            .write_message(Message::Text(String::from("not json")))
+            .unwrap();
+        let reply: Value =
+            serde_json::from_str(&client.read_message().unwrap().into_text().unwrap()).unwrap();
+        assert_eq!(reply["error"]["code"], "invalid_request");
+        client
+            .write_message(Message::Text(String::from(
+                r#"{"version": 1, "command": "create_game", "payload": {"name": "alice"}}"#,
+            )))
+            .unwrap();
+        client.read_message().unwrap();
+        drop(client);
+
+        // and a client closing the websocket is let go cleanly
+        let (mut client, _) =
+            tungstenite::client(&url, TcpStream::connect(address).unwrap()).unwrap();
+        client.read_message().unwrap();
+        client
+            .write_message(Message::Text(String::from(
+                r#"{"version": 1, "command": "list_games"}"#,
+            )))
+            .unwrap();
+        let reply: Value =
+            serde_json::from_str(&client.read_message().unwrap().into_text().unwrap()).unwrap();
+        assert_eq!(reply["payload"]["games"][0]["dark"], "alice");
+        client.close(None).unwrap();
+        while client.read_message().is_ok() {}
+        assert!(serve_two.join().is_ok());
+    }
+
+    #[test]
+    fn test_clock_time_controls() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let remaining =
+            |clock: &Clock, side: Side, ms: u64| clock.get_remaining(side, at(ms)).as_millis();
+
+        let mut clock = Clock::init(TimeControl::Fischer {
+            base: Duration::from_millis(1000),
+            increment: Duration::from_millis(100),
+        });
+        clock.start(Side::Dark, at(0));
+        assert_eq!(remaining(&clock, Side::Dark, 300), 700);
+        clock.complete_turn(at(300));
+        assert_eq!(clock.get_running(), Some(Side::Light));
+        assert_eq!(remaining(&clock, Side::Dark, 500), 800);
+        assert_eq!(remaining(&clock, Side::Light, 500), 800);
+        assert_eq!(clock.get_flagged(at(1299)), None);
+        assert_eq!(clock.get_flagged(at(1300)), Some(Side::Light));
+
+        // time used within the delay is given back
+        let mut clock = Clock::init(TimeControl::Bronstein {
+            base: Duration::from_millis(1000),
+            delay: Duration::from_millis(200),
+        });
+        clock.start(Side::Dark, at(0));
+        clock.complete_turn(at(150));
+        assert_eq!(remaining(&clock, Side::Dark, 150), 1000);
+        clock.complete_turn(at(650));
+        assert_eq!(remaining(&clock, Side::Light, 650), 700);
+
+        // every move gets the same time and unused time is lost
+        let mut clock = Clock::init(TimeControl::PerMove {
+            time: Duration::from_millis(500),
+        });
+        clock.start(Side::Dark, at(0));
+        clock.complete_turn(at(100));
+        assert_eq!(remaining(&clock, Side::Dark, 100), 500);
+        assert_eq!(clock.get_flagged(at(600)), Some(Side::Light));
+        clock.stop(at(300));
+        assert_eq!(clock.get_running(), None);
+        assert_eq!(remaining(&clock, Side::Light, 900), 300);
+    }
+
+    #[test]
+    fn test_lose_on_time() {
+        let mut checkers = Checkers::init();
+        checkers.set_time_control(TimeControl::Fischer {
+            base: Duration::from_millis(50),
+            increment: Duration::ZERO,
+        });
+        checkers.setup_players(String::from("alice"), String::from("bob"));
+        assert_eq!(
+            checkers.get_clock().unwrap().get_running(),
+            Some(Side::Dark)
+        );
+        assert!(checkers
+            .move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]))
+            .is_ok());
+        assert!(!checkers.check_time());
+        sleep(Duration::from_millis(80));
+        assert!(checkers.check_time());
+        assert_eq!(checkers.get_result(), GameResult::WinOnTime(Side::Dark));
+        assert_eq!(checkers.get_clock().unwrap().get_running(), None);
+        assert_eq!(
+            checkers.move_piece(Side::Light, &Move::init((5, 0), vec![(4, 1)])),
+            Err(MoveError::GameCompleted)
+        );
+        assert!(checkers.to_pdn().to_string().contains("1-0"));
+    }
+
+    #[test]
+    fn test_undo_restores_clocks() {
+        let mut checkers = Checkers::init();
+        checkers.set_time_control(TimeControl::Fischer {
+            base: Duration::from_millis(1000),
+            increment: Duration::from_millis(500),
+        });
+        checkers.setup_players(String::from("alice"), String::from("bob"));
+        assert!(checkers
+            .move_piece(Side::Dark, &Move::init((2, 1), vec![(3, 0)]))
+            .is_ok());
+        sleep(Duration::from_millis(30));
+        let now = Instant::now();
+        let clock = checkers.get_clock().unwrap();
+        assert!(clock.get_remaining(Side::Dark, now) > Duration::from_millis(1400));
+        assert!(clock.get_remaining(Side::Light, now) <= Duration::from_millis(970));
+
+        // the increment dark got for the move and the time light used are both given back
+        assert!(checkers.undo_move().is_some());
+        let now = Instant::now();
+        let clock = checkers.get_clock().unwrap();
+        assert_eq!(clock.get_running(), Some(Side::Dark));
+        assert!(clock.get_remaining(Side::Dark, now) <= Duration::from_millis(1000));
+        assert!(clock.get_remaining(Side::Dark, now) > Duration::from_millis(900));
+        assert_eq!(
+            clock.get_remaining(Side::Light, now),
+            Duration::from_millis(1000)
+        );
+    }
+
+    #[test]
+    fn test_server_game_clocks() {
+        let server = Server::init();
+        let mut alice = Connection::init(&server);
+        let mut bob = Connection::init(&server);
+        let mut carol = Connection::init(&server);
+        assert_eq!(
+            send(&server, &mut alice, Encoding::Text, "create_game alice fischer 60000"),
+            vec!["create_game:\"invalid time control, expected fischer <base ms> <increment ms>, bronstein <base ms> <delay ms> or per_move <ms>\""]
+        );
+        send(
+            &server,
+            &mut alice,
+            Encoding::Text,
+            "create_game alice fischer 60000 1000",
+        );
+        let replies = send_json(
+            &server,
+            &mut carol,
+            json!({
+                "version": 1,
+                "command": "create_game",
+                "payload": {"name": "carol", "time_control": {"type": "per_move", "time": 50}}
+            }),
+        );
+        assert_eq!(replies[0]["status"], "ok");
+        assert_eq!(
+            send(&server, &mut bob, Encoding::Text, "list_games"),
+            vec!["games:[1 alice - waiting fischer 60000 1000, 2 carol - waiting per_move 50]"]
+        );
+
+        // every board carries the clocks
+        let replies = send(&server, &mut bob, Encoding::Text, "join_game 1 bob");
+        assert!(replies[3].starts_with("clock:dark="));
+        assert!(replies[3].ends_with(" light=60000"));
+        let replies = send_json(
+            &server,
+            &mut alice,
+            json!({"version": 1, "command": "move_piece", "payload": {"path": [11, 15]}}),
+        );
+        let clock = &replies[1]["payload"]["clock"];
+        assert_eq!(clock["running"], "light");
+        assert!(clock["dark"].as_u64().unwrap() > 60000);
+        assert!((59000..=60000).contains(&clock["light"].as_u64().unwrap()));
+
+        // the clocks wait for both players, even in a game loaded before then
+        let replies = send(&server, &mut carol, Encoding::Text, "load_pdn 1. 11-15 *");
+        assert_eq!(replies[0], "load_pdn:\"loaded 1 moves\"");
+        sleep(Duration::from_millis(80));
+        server.check_clocks();
+        assert_eq!(
+            send(&server, &mut bob, Encoding::Text, "list_games"),
+            vec!["games:[1 alice bob playing fischer 60000 1000, 2 carol - waiting per_move 50]"]
+        );
+
+        // a flag falling ends the game for everyone in it without anyone moving
+        let mut dave = Connection::init(&server);
+        send(&server, &mut dave, Encoding::Text, "join_game 2 dave");
+        received(&alice, Encoding::Text);
+        received(&carol, Encoding::Text);
+        sleep(Duration::from_millis(80));
+        server.check_clocks();
+        for connection in [&carol, &dave] {
+            let events = received(connection, Encoding::Text);
+            assert_eq!(
+                events[0],
+                "status: game completed, carol (dark) is the winner on time!"
+            );
+            assert_eq!(events[3], "clock:dark=50 light=0");
+        }
+        assert!(received(&alice, Encoding::Text).is_empty());
+    }
+
+    #[test]
+    fn test_parse_search_limits() {
+        assert_eq!(parse_search_limits(&[]), SearchLimits::init());
+        assert_eq!(parse_search_limits(&["5"]).depth, Some(5));
+        let limits = parse_search_limits(&["time", "60000", "inc", "1000"]);
+        assert_eq!(limits.depth, None);
+        assert_eq!(limits.time_left, Some(Duration::from_secs(60)));
+        assert_eq!(limits.increment, Duration::from_secs(1));
+        let limits = parse_search_limits(&["nodes", "100", "depth", "4"]);
+        assert_eq!(limits.nodes, Some(100));
+        assert_eq!(limits.depth, Some(4));
+
+        // bad limits are rejected rather than searched without
+        for (args, message) in [
+            ("foo", "invalid depth foo"),
+            ("depth abc", "invalid value for depth"),
+            ("depth 4294967296", "invalid value for depth"),
+            ("nodes 100 depth", "missing value for depth"),
+            (
+                "speed 3",
+                "unknown search limit speed, expected depth, nodes, movetime, time or inc",
+            ),
+        ] {
+            assert_eq!(
+                Command::parse_text(&format!("best_move {}", args)),
+                Err(ProtocolError::init(ErrorCode::InvalidArgument, message))
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_with_threads() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let limits = SearchLimits::init_with_depth(6);
+        let mut engine = Engine::init();
+        engine.options.threads = 4;
+        let result = engine.best_move(&position, &limits).unwrap();
+        assert_eq!(result.depth, 6);
+        let mut line = position;
+        for mv in &result.pv {
+            assert!(line.legal_moves().contains(mv));
+            line = line.make_move(mv);
+        }
+
+        // every thread finds the same forced win
+        assert!(checkers.load_fen("B:W9:B5,23").is_ok());
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let result = engine.best_move(&position, &limits).unwrap();
+        assert_eq!(result.score, WIN_SCORE - 1);
+    }
+
+    /// Position along with its perft counts from depth 1
+    type PerftCase = (&'static str, &'static [u64]);
+
+    const PERFT_CASES: [PerftCase; 3] = [
+        // a king that can jump around a loop of men back to its own square
+        (
+            "B:W6,7,14,15,22,23,28,29:B4,5,K10",
+            &[6, 40, 135, 786, 3140, 17493],
+        ),
+        // a man kinged part way through a jump chain has to stop there
+        ("B:W9,16,26,27,32:B1,8,19,22", &[1, 7, 9, 36, 98, 256, 968]),
+        // kings on both sides with captures in every direction
+        (
+            "W:W28,30,K8,K18:B11,14,15,23,K25",
+            &[3, 17, 76, 453, 2594, 13849],
+        ),
+    ];
+
+    #[test]
+    fn test_perft_start_position() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        for (depth, count) in [7, 49, 302, 1469, 7361, 36768].iter().enumerate() {
+            assert_eq!(engine::perft(&position, depth as u32 + 1), *count);
+        }
+    }
+
+    #[test]
+    fn test_perft_capture_positions() {
+        for (fen, counts) in PERFT_CASES {
+            let (board, turn) = CheckersBoard::init_from_fen(fen).unwrap();
+            let position = Position::init(board, turn);
+            for (depth, count) in counts.iter().enumerate() {
+                assert_eq!(engine::perft(&position, depth as u32 + 1), *count);
             }
         }
-        let mut piece = board.get(0).unwrap().get(0).unwrap().clone();
-        piece.blocked = false;
-        piece.occupant.owner = "test1".to_string();
-        piece.occupant.loc = (1, 0);
-        piece.occupant.direction = 1;
-        board[1][0] = piece.clone();
-
-        piece.occupant.owner = "test2".to_string();
-        piece.occupant.loc = (2, 1);
-        piece.occupant.direction = -1;
-        board[2][1] = piece.clone();
-
-        let output = checkers.move_piece("test1".to_string(), (1, 0), (3, 2));
-        assert!(output);
-        assert_eq!(checkers.get_turn(), "test2");
-        assert!(checkers.is_completed());
-        assert_eq!(checkers.get_winner(), "test1");
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut checkers = setup();
+        let position = Position::init(*checkers.get_board(), Side::Dark);
+        let divided = engine::divide(&position, 4);
+        assert_eq!(divided.len(), 7);
+        assert_eq!(
+            divided.iter().map(|(_, count)| count).sum::<u64>(),
+            engine::perft(&position, 4)
+        );
+        assert!(engine::divide(&position, 0).is_empty());
+    }
+
+    const SAMPLE_PDN: &str = r#"[Event "Club match"]
+[Black "Ann"]
+[White "Bo \"the wall\""]
+[Result "1-0"]
+
+{Single corner opening} 1. 11-15 22-18 2. 15x22 {forced} 25x18 (2... 26x17 3. 9-13)
+3. 12-16! 24-20 1-0
+
+[Black "Cy"]
+[White "Di"]
+1. 9-14 23-19 *"#;
+
+    #[test]
+    fn test_pdn_parse() {
+        let games = PdnGame::parse_all(SAMPLE_PDN).unwrap();
+        assert_eq!(games.len(), 2);
+        let game = &games[0];
+        assert_eq!(game.get_tag("Event"), Some("Club match"));
+        assert_eq!(game.get_tag("White"), Some("Bo \"the wall\""));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(
+            game.mainline.comment,
+            Some(String::from("Single corner opening"))
+        );
+
+        let moves = &game.mainline.moves;
+        assert_eq!(moves.len(), 6);
+        assert_eq!(moves[2].squares, vec![15, 22]);
+        assert!(moves[2].capture);
+        assert_eq!(moves[2].comment, Some(String::from("forced")));
+        assert_eq!(moves[3].variations.len(), 1);
+        assert_eq!(moves[3].variations[0].moves.len(), 2);
+        assert_eq!(moves[4].to_string(), "12-16");
+        assert_eq!(games[1].result, "*");
+        assert_eq!(games[1].get_tag("Black"), Some("Cy"));
+
+        // annotation glyphs are skipped and a variation's result doesn't end the game
+        let game = PdnGame::parse("1. 11-15 $1 22-18 (22-17 $2 *) 2. 15x22 0-1").unwrap();
+        assert_eq!(game.mainline.moves.len(), 3);
+        assert_eq!(game.mainline.moves[1].variations[0].moves.len(), 1);
+        assert_eq!(game.result, "0-1");
+        // a variation before the first move is an alternative to it
+        let game = PdnGame::parse("{start} (1. 9-13) 1. 11-15 22-18 *").unwrap();
+        assert_eq!(game.mainline.moves.len(), 2);
+        assert_eq!(game.mainline.moves[0].variations.len(), 1);
+        assert_eq!(
+            game.mainline.moves[0].variations[0].moves[0].to_string(),
+            "9-13"
+        );
+        assert!(game
+            .to_string()
+            .contains("{start} 1. 11-15 (1. 9-13) 1... 22-18 *"));
+
+        assert_eq!(
+            PdnGame::parse("1. 11-15 (22-18"),
+            Err(PdnError::UnbalancedVariation)
+        );
+        assert_eq!(
+            PdnGame::parse("1. 11-15 {open"),
+            Err(PdnError::UnterminatedComment)
+        );
+        assert_eq!(
+            PdnGame::parse("1. 11-45"),
+            Err(PdnError::InvalidMove(String::from("11-45")))
+        );
+    }
+
+    #[test]
+    fn test_pdn_replay() {
+        let mut checkers = setup();
+        let game = PdnGame::parse(SAMPLE_PDN).unwrap();
+        assert!(checkers.load_pdn(&game).is_ok());
+        assert_eq!(checkers.get_move_history().len(), 6);
+        // the players keep their names rather than taking the record's
+        assert_eq!(checkers.get_player(Side::Dark), Some(String::from("test1")));
+        assert_eq!(checkers.get_move_history()[0].path(), vec![(2, 5), (3, 4)]);
+        assert_eq!(checkers.get_turn(), Side::Dark);
+
+        // only the ends of a multi-jump have to be given
+        let game = PdnGame::parse("1. 11-15 22-18 2. 15x22 26x17").unwrap();
+        assert!(checkers.load_pdn(&game).is_ok());
+        let game = PdnGame::parse("1. 11-15 22-18 2. 15-19").unwrap();
+        let before = checkers.get_position_hash();
+        assert_eq!(
+            checkers.load_pdn(&game),
+            Err(PdnError::UnknownMove(String::from("15-19")))
+        );
+        // a record that fails to load leaves the game as it was
+        assert_eq!(checkers.get_position_hash(), before);
+        assert_eq!(checkers.get_move_history().len(), 4);
+
+        // the FEN tag gives the position the moves are played from
+        let game = PdnGame::parse("[SetUp \"1\"]\n[FEN \"W:W18,K30:B10\"]\n1... 18-14 *").unwrap();
+        assert!(checkers.load_pdn(&game).is_ok());
+        assert_eq!(checkers.get_fen(), "B:W14,K30:B10");
+        let game = PdnGame::parse("[FEN \"X:W18:B10\"]\n1. 18-14 *").unwrap();
+        assert!(matches!(
+            checkers.load_pdn(&game),
+            Err(PdnError::InvalidFen(_))
+        ));
+        assert_eq!(checkers.get_fen(), "B:W14,K30:B10");
+
+        // a game set up from a position exports its FEN tag and loads back the same
+        let pdn = checkers.to_pdn().to_string();
+        assert!(pdn.contains("[FEN \"W:W18,K30:B10\"]"));
+        assert!(pdn.contains("1... 18-14 *"));
+        let mut loaded = Checkers::init();
+        assert!(loaded.load_pdn(&PdnGame::parse(&pdn).unwrap()).is_ok());
+        assert_eq!(loaded.get_position_hash(), checkers.get_position_hash());
+    }
+
+    #[test]
+    fn test_pdn_export() {
+        let mut checkers = setup();
+        for (player, from, to) in [
+            (Side::Dark, (2, 7), (3, 6)),
+            (Side::Light, (5, 4), (4, 5)),
+            (Side::Dark, (3, 6), (5, 4)),
+        ] {
+            assert!(checkers
+                .move_piece(player, &Move::init(from, vec![to]))
+                .is_ok());
+        }
+        let pdn = checkers.to_pdn().to_string();
+        assert!(pdn.contains("[Black \"test1\"]"));
+        assert!(pdn.contains("1. 12-16 23-19 2. 16x23 *"));
+
+        // an exported game loads back to the same position
+        let mut loaded = Checkers::init();
+        assert!(loaded.load_pdn(&PdnGame::parse(&pdn).unwrap()).is_ok());
+        assert_eq!(loaded.get_position_hash(), checkers.get_position_hash());
+        // a game without players takes their names from the record
+        assert_eq!(loaded.get_player(Side::Dark), Some(String::from("test1")));
+
+        // comments and variations survive a round trip
+        let game = PdnGame::parse(SAMPLE_PDN).unwrap();
+        assert_eq!(PdnGame::parse(&game.to_string()), Ok(game));
     }
 }
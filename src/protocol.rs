@@ -0,0 +1,178 @@
+/// Messages exchanged with websocket clients. Clients either speak the original text protocol,
+/// where a command is a line of words and replies are `<name>:<value>` strings, or JSON where
+/// every message is an object carrying the protocol version.
+mod command;
+mod error;
+mod reply;
+
+pub use command::{path_to_move, Command, SquareInput};
+pub use error::{ErrorCode, ProtocolError};
+pub use reply::{
+    BoardView, DivideView, GameOverView, GameSummary, MoveView, Payload, Reply, SearchView,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version of the JSON protocol, requests for any other version are rejected
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How the messages of a connection are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Text,
+    Json,
+}
+
+/// A message received from a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub id: Option<Value>, // echoed back in the response to a JSON request
+    pub name: String,      // name of the command as sent, even if it isn't recognised
+    pub command: Result<Command, ProtocolError>,
+}
+
+/// Fields every JSON request has besides its command
+#[derive(Deserialize)]
+struct Envelope {
+    version: Option<u32>,
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(default)]
+    command: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ResponseMessage<'a> {
+    version: u32,
+    id: &'a Option<Value>,
+    command: &'a str,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<&'a Payload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a ProtocolError>,
+}
+
+#[derive(Serialize)]
+struct EventMessage<'a> {
+    version: u32,
+    event: &'static str,
+    payload: &'a Payload,
+}
+
+impl Request {
+    pub fn decode(encoding: Encoding, message: &str) -> Request {
+        match encoding {
+            Encoding::Text => {
+                let name = message.split_whitespace().next().unwrap_or_default();
+                return Request {
+                    id: None,
+                    name: name.to_string(),
+                    command: Command::parse_text(message),
+                };
+            }
+            Encoding::Json => return Request::decode_json(message),
+        }
+    }
+
+    /// Decode a JSON request such as `{"version": 1, "id": "a", "command": "undo"}`
+    fn decode_json(message: &str) -> Request {
+        let value: Value = match serde_json::from_str(message) {
+            Ok(value) => value,
+            Err(e) => {
+                return Request {
+                    id: None,
+                    name: String::new(),
+                    command: Err(ProtocolError::init(
+                        ErrorCode::InvalidRequest,
+                        format!("message is not JSON, {}", e),
+                    )),
+                }
+            }
+        };
+        let envelope = if value.is_object() {
+            Envelope::deserialize(&value).ok()
+        } else {
+            None
+        };
+        let Some(envelope) = envelope else {
+            return Request {
+                id: None,
+                name: String::new(),
+                command: Err(ProtocolError::init(
+                    ErrorCode::InvalidRequest,
+                    "request must be an object with a version, an optional id and a command",
+                )),
+            };
+        };
+        let mut request = Request {
+            id: envelope.id,
+            name: envelope.command.unwrap_or_default(),
+            command: Err(ProtocolError::init(
+                ErrorCode::InvalidRequest,
+                "request is missing its version",
+            )),
+        };
+        match envelope.version {
+            Some(PROTOCOL_VERSION) => {}
+            Some(version) => {
+                request.command = Err(ProtocolError::init(
+                    ErrorCode::UnsupportedVersion,
+                    format!(
+                        "protocol version {} is not supported, the server speaks version {}",
+                        version, PROTOCOL_VERSION
+                    ),
+                ));
+                return request;
+            }
+            None => return request,
+        }
+        request.command = Command::deserialize(&value).map_err(|e| {
+            let message = e.to_string();
+            let code = if message.starts_with("unknown variant") {
+                ErrorCode::UnknownCommand
+            } else {
+                ErrorCode::InvalidArgument
+            };
+            ProtocolError::init(code, message)
+        });
+        return request;
+    }
+
+    /// Encode a reply to this request
+    pub fn encode(&self, encoding: Encoding, reply: &Reply) -> Vec<String> {
+        return encode(encoding, reply, &self.name, &self.id);
+    }
+}
+
+/// Encode a reply in the format of a connection, `name` is the command being responded to
+pub fn encode(encoding: Encoding, reply: &Reply, name: &str, id: &Option<Value>) -> Vec<String> {
+    match encoding {
+        Encoding::Text => match reply {
+            Reply::Response(Ok(payload)) | Reply::Event(payload) => payload.to_text(),
+            Reply::Response(Err(error)) if error.code == ErrorCode::UnknownCommand => {
+                vec![error.message.clone()]
+            }
+            Reply::Response(Err(error)) => vec![format!("{}:\"{}\"", name, error.message)],
+        },
+        Encoding::Json => {
+            let message = match reply {
+                Reply::Response(result) => serde_json::to_string(&ResponseMessage {
+                    version: PROTOCOL_VERSION,
+                    id,
+                    command: name,
+                    status: if result.is_ok() { "ok" } else { "error" },
+                    payload: result.as_ref().ok(),
+                    error: result.as_ref().err(),
+                }),
+                Reply::Event(payload) => serde_json::to_string(&EventMessage {
+                    version: PROTOCOL_VERSION,
+                    event: payload.get_name(),
+                    payload,
+                }),
+            };
+            // the messages are plain structs of strings and numbers, which always serialise
+            return message.into_iter().collect();
+        }
+    }
+}
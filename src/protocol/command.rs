@@ -0,0 +1,395 @@
+use super::{ErrorCode, ProtocolError};
+use crate::engine::SearchLimits;
+use crate::game::{Move, Square, TimeControl};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Reply to a text command that isn't recognised
+pub const UNKNOWN_COMMAND: &str = "Unknown command. Send \"help\" to see all available commands.";
+
+/// A square in a request, given as a 1-32 number, algebraic notation like `c3` or a
+/// `[row, col]` pair
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum SquareInput {
+    Number(u32),
+    Cord(usize, usize),
+    Name(String),
+}
+
+impl SquareInput {
+    /// Row and column of the square. Row and column pairs are kept as they are, even off the
+    /// board, so a move to them is rejected by the game rather than failing to parse.
+    ///
+    /// **Returns** - None if a number or name isn't a playable square
+    pub fn get_cord(&self) -> Option<(usize, usize)> {
+        match self {
+            SquareInput::Number(number) => Square::init(*number).map(|square| square.get_cord()),
+            SquareInput::Cord(x, y) => Some((*x, *y)),
+            SquareInput::Name(name) => Square::parse(name).map(|square| square.get_cord()),
+        }
+    }
+
+    /// **Returns** - None if the input isn't a playable square
+    pub fn get_square(&self) -> Option<Square> {
+        match self {
+            SquareInput::Number(number) => Square::init(*number),
+            SquareInput::Cord(x, y) => Square::init_from_cord((*x, *y)),
+            SquareInput::Name(name) => Square::parse(name),
+        }
+    }
+}
+
+/// Limits of a `best_move` search, times are in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchRequest {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>,
+    pub time: Option<u64>, // time left on the clock of the side to move
+    pub inc: Option<u64>,  // time added to the clock after each move
+}
+
+impl SearchRequest {
+    /// Read the arguments of a text "best_move" command, a single number is taken as the depth
+    /// and otherwise every limit is a name followed by its value
+    fn parse_args(args: &[&str]) -> Result<SearchRequest, ProtocolError> {
+        let mut request = SearchRequest::default();
+        if let [depth] = args {
+            let Ok(depth) = depth.parse::<u32>() else {
+                return Err(invalid_argument(format!("invalid depth {}", depth)));
+            };
+            request.depth = Some(depth);
+            return Ok(request);
+        }
+        for pair in args.chunks(2) {
+            let [name, value] = pair else {
+                return Err(invalid_argument(format!("missing value for {}", pair[0])));
+            };
+            let invalid_value = || invalid_argument(format!("invalid value for {}", name));
+            match *name {
+                "depth" => request.depth = Some(value.parse().map_err(|_| invalid_value())?),
+                "nodes" => request.nodes = Some(value.parse().map_err(|_| invalid_value())?),
+                "movetime" => request.movetime = Some(value.parse().map_err(|_| invalid_value())?),
+                "time" => request.time = Some(value.parse().map_err(|_| invalid_value())?),
+                "inc" => request.inc = Some(value.parse().map_err(|_| invalid_value())?),
+                _ => {
+                    return Err(invalid_argument(format!(
+                        "unknown search limit {}, expected depth, nodes, movetime, time or inc",
+                        name
+                    )))
+                }
+            }
+        }
+        return Ok(request);
+    }
+
+    pub fn get_limits(&self) -> SearchLimits {
+        let mut limits = SearchLimits::init();
+        limits.nodes = self.nodes;
+        limits.movetime = self.movetime.map(Duration::from_millis);
+        limits.time_left = self.time.map(Duration::from_millis);
+        limits.increment = Duration::from_millis(self.inc.unwrap_or(0));
+        // any node or time limit replaces the default depth
+        if limits.nodes.is_some() || limits.movetime.is_some() || limits.time_left.is_some() {
+            limits.depth = None;
+        }
+        if self.depth.is_some() {
+            limits.depth = self.depth;
+        }
+        return limits;
+    }
+}
+
+/// Time control of a new game, times are in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TimeControlRequest {
+    Fischer { base: u64, increment: u64 },
+    Bronstein { base: u64, delay: u64 },
+    PerMove { time: u64 },
+}
+
+impl TimeControlRequest {
+    /// Read a time control given as text, such as "fischer 300000 5000", "bronstein 300000
+    /// 5000" or "per_move 30000"
+    fn parse_args(args: &[&str]) -> Option<TimeControlRequest> {
+        let times: Vec<u64> = args
+            .get(1..)?
+            .iter()
+            .map(|time| time.parse::<u64>().ok())
+            .collect::<Option<_>>()?;
+        match (args[0], times.as_slice()) {
+            ("fischer", [base, increment]) => Some(TimeControlRequest::Fischer {
+                base: *base,
+                increment: *increment,
+            }),
+            ("bronstein", [base, delay]) => Some(TimeControlRequest::Bronstein {
+                base: *base,
+                delay: *delay,
+            }),
+            ("per_move", [time]) => Some(TimeControlRequest::PerMove { time: *time }),
+            _ => None,
+        }
+    }
+
+    pub fn get_time_control(&self) -> TimeControl {
+        match *self {
+            TimeControlRequest::Fischer { base, increment } => TimeControl::Fischer {
+                base: Duration::from_millis(base),
+                increment: Duration::from_millis(increment),
+            },
+            TimeControlRequest::Bronstein { base, delay } => TimeControl::Bronstein {
+                base: Duration::from_millis(base),
+                delay: Duration::from_millis(delay),
+            },
+            TimeControlRequest::PerMove { time } => TimeControl::PerMove {
+                time: Duration::from_millis(time),
+            },
+        }
+    }
+}
+
+/// Everything a client can ask of the server. JSON requests name the command in snake_case
+/// and give its fields as the payload, e.g.
+/// `{"version": 1, "id": 3, "command": "move_piece", "payload": {"path": [11, 15]}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", content = "payload", rename_all = "snake_case")]
+pub enum Command {
+    Help,
+    Status,
+    ListGames,
+    CreateGame {
+        name: String,
+        #[serde(default)]
+        time_control: Option<TimeControlRequest>, // None for an untimed game
+    },
+    JoinGame {
+        game: u32,
+        name: String,
+    },
+    SetPlayerName {
+        name: String,
+    },
+    Watch {
+        game: u32,
+    },
+    Reconnect {
+        token: String,
+    },
+    LegalMoves,
+    MovePiece {
+        path: Vec<SquareInput>,
+    },
+    Undo,
+    Redo,
+    History,
+    Square {
+        square: SquareInput,
+    },
+    Fen,
+    LoadFen {
+        fen: String,
+    },
+    ExportPdn,
+    LoadPdn {
+        pdn: String,
+    },
+    SetOption {
+        name: String,
+        value: usize,
+    },
+    Perft {
+        depth: u32,
+    },
+    Divide {
+        depth: u32,
+    },
+    Evaluate,
+    BestMove(Option<SearchRequest>), // None if a JSON request leaves out the payload
+    Stop,
+}
+
+impl Command {
+    /// Parse a text protocol command, named by its first word and followed by its arguments
+    /// separated by whitespace. Game records and FEN strings take the rest of the message.
+    pub fn parse_text(text: &str) -> Result<Command, ProtocolError> {
+        let text = text.trim();
+        let (name, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let command = match name {
+            "help" => Command::Help,
+            "donger" | "status" => Command::Status,
+            "list_games" => Command::ListGames,
+            "create_game" => match args.split_first() {
+                Some((name, [])) => Command::CreateGame {
+                    name: name.to_string(),
+                    time_control: None,
+                },
+                Some((name, time_control)) => match TimeControlRequest::parse_args(time_control) {
+                    Some(time_control) => Command::CreateGame {
+                        name: name.to_string(),
+                        time_control: Some(time_control),
+                    },
+                    None => return Err(invalid_argument(
+                        "invalid time control, expected fischer <base ms> <increment ms>, bronstein <base ms> <delay ms> or per_move <ms>",
+                    )),
+                },
+                None => return Err(invalid_argument("missing player name")),
+            },
+            "join_game" => match (args.first().map(|id| id.parse::<u32>()), args.get(1)) {
+                (Some(Ok(game)), Some(name)) => Command::JoinGame {
+                    game,
+                    name: name.to_string(),
+                },
+                (Some(Ok(_)), None) => return Err(invalid_argument("missing player name")),
+                _ => return Err(invalid_argument("missing or invalid game id")),
+            },
+            "set_player_name" => match args.first() {
+                Some(name) => Command::SetPlayerName {
+                    name: name.to_string(),
+                },
+                None => return Err(invalid_argument("missing player name")),
+            },
+            "reconnect" => match args.first() {
+                Some(token) => Command::Reconnect {
+                    token: token.to_string(),
+                },
+                None => return Err(invalid_argument("missing session token")),
+            },
+            "watch" => match args.first().map(|id| id.parse::<u32>()) {
+                Some(Ok(game)) => Command::Watch { game },
+                _ => return Err(invalid_argument("missing or invalid game id")),
+            },
+            "legal_moves" => Command::LegalMoves,
+            "move_piece" => match parse_path(&args) {
+                Some(path) => Command::MovePiece { path },
+                None => return Err(invalid_argument(
+                    "failed to parse move, expected row and column pairs, square numbers or algebraic squares",
+                )),
+            },
+            "undo" => Command::Undo,
+            "redo" => Command::Redo,
+            "history" => Command::History,
+            "square" => match parse_square_input(&args) {
+                Some(square) => Command::Square { square },
+                None => return Err(invalid_argument("not a playable square")),
+            },
+            "fen" => Command::Fen,
+            "load_fen" => Command::LoadFen {
+                fen: rest.to_string(),
+            },
+            "export_pdn" => Command::ExportPdn,
+            "load_pdn" => Command::LoadPdn {
+                pdn: rest.to_string(),
+            },
+            "set_option" => match (args.first(), args.get(1).map(|v| v.parse::<usize>())) {
+                (Some(name), Some(Ok(value))) => Command::SetOption {
+                    name: name.to_string(),
+                    value,
+                },
+                (Some(name), _) => {
+                    return Err(invalid_argument(format!(
+                        "invalid option or value for {}",
+                        name
+                    )))
+                }
+                (None, _) => return Err(invalid_argument("missing option name")),
+            },
+            "perft" | "divide" => {
+                let Some(Ok(depth)) = args.first().map(|depth| depth.parse::<u32>()) else {
+                    return Err(invalid_argument("missing or invalid depth"));
+                };
+                if name == "perft" {
+                    Command::Perft { depth }
+                } else {
+                    Command::Divide { depth }
+                }
+            }
+            "evaluate" => Command::Evaluate,
+            "best_move" => Command::BestMove(Some(SearchRequest::parse_args(&args)?)),
+            "stop" => Command::Stop,
+            _ => {
+                return Err(ProtocolError::init(
+                    ErrorCode::UnknownCommand,
+                    UNKNOWN_COMMAND,
+                ))
+            }
+        };
+        return Ok(command);
+    }
+}
+
+fn invalid_argument(message: impl Into<String>) -> ProtocolError {
+    return ProtocolError::init(ErrorCode::InvalidArgument, message);
+}
+
+/// Parse the squares of a text `move_piece` command, starting with the piece to move followed
+/// by every square it lands on. Squares are given as row and column pairs like `2 5 3 4`,
+/// standard 1-32 numbers joined by `-` or `x` like `11-15` or `11x18x25`, or algebraic
+/// notation like `c3 d4` or `c3-d4`. Two numbers on their own like `11 15` are square numbers,
+/// as a single row and column pair can't be a move.
+///
+/// **Returns** - None if a square can't be parsed
+fn parse_path(args: &[&str]) -> Option<Vec<SquareInput>> {
+    let is_notation = args
+        .iter()
+        .any(|arg| arg.contains(['-', 'x']) || arg.starts_with(|c: char| c.is_ascii_alphabetic()));
+    if is_notation {
+        return args
+            .iter()
+            .flat_map(|arg| arg.split(['-', 'x']))
+            .filter(|square| !square.is_empty())
+            .map(|square| Square::parse(square).map(|_| SquareInput::Name(square.to_string())))
+            .collect();
+    }
+    let cords: Vec<usize> = args
+        .iter()
+        .map(|cord| cord.parse::<usize>().ok())
+        .collect::<Option<_>>()?;
+    if let [from, to] = cords[..] {
+        if [from, to].iter().all(|number| (1..=32).contains(number)) {
+            return Some(vec![
+                SquareInput::Number(from as u32),
+                SquareInput::Number(to as u32),
+            ]);
+        }
+    }
+    if !cords.len().is_multiple_of(2) {
+        return None;
+    }
+    return Some(
+        cords
+            .chunks_exact(2)
+            .map(|c| SquareInput::Cord(c[0], c[1]))
+            .collect(),
+    );
+}
+
+/// Parse a single square given as a row and column pair, a 1-32 number or algebraic notation
+///
+/// **Returns** - None if the square isn't playable
+fn parse_square_input(args: &[&str]) -> Option<SquareInput> {
+    match args {
+        [square] => Some(SquareInput::Name(square.to_string())),
+        [x, y] => Some(SquareInput::Cord(x.parse().ok()?, y.parse().ok()?)),
+        _ => None,
+    }
+    .filter(|square| square.get_square().is_some())
+}
+
+/// Move along a path of squares, the first square being the piece to move
+///
+/// **Returns** - None if a square isn't playable or no landing square is given
+pub fn path_to_move(path: &[SquareInput]) -> Option<Move> {
+    let cords: Vec<(usize, usize)> = path
+        .iter()
+        .map(SquareInput::get_cord)
+        .collect::<Option<_>>()?;
+    let (origin, landings) = cords.split_first()?;
+    if landings.is_empty() {
+        return None;
+    }
+    return Some(Move::init(*origin, landings.to_vec()));
+}
@@ -0,0 +1,58 @@
+use crate::game::MoveError;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+
+/// Kind of failure a request is answered with, sent to JSON clients as a snake_case code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidRequest,     // message that isn't a request at all
+    UnsupportedVersion, // request for a protocol version the server doesn't speak
+    UnknownCommand,
+    InvalidArgument, // command arguments that are missing or can't be parsed
+    NotPlaying,      // command that needs the client to be playing in a game
+    AlreadyPlaying,  // request to join or watch a game while playing in or watching one
+    NoSuchGame,
+    NoSuchSession, // reconnect with a token the server didn't give out
+    GameFull,
+    GameCompleted,
+    NotYourTurn,
+    IllegalMove,
+    NoMoves,     // nothing to play, take back or redo
+    ServerError, // something went wrong on the server's side
+}
+
+/// Reason a request failed, `message` is meant to be shown to the player
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProtocolError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ProtocolError {
+    pub fn init(code: ErrorCode, message: impl Into<String>) -> ProtocolError {
+        return ProtocolError {
+            code,
+            message: message.into(),
+        };
+    }
+
+    /// Error for a move the game rejected, prefixed with `context` such as the destination
+    pub fn init_from_move_error(error: &MoveError, context: &str) -> ProtocolError {
+        let code = match error {
+            MoveError::GameCompleted => ErrorCode::GameCompleted,
+            MoveError::NotYourTurn => ErrorCode::NotYourTurn,
+            _ => ErrorCode::IllegalMove,
+        };
+        return ProtocolError::init(code, format!("{}, {}", context, error));
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ProtocolError {}
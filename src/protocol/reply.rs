@@ -0,0 +1,415 @@
+use super::ProtocolError;
+use crate::engine::SearchResult;
+use crate::game::{Checkers, Clock, GameResult, Move, Side, Square};
+use serde::Serialize;
+use std::time::Instant;
+
+/// A move as sent to clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MoveView {
+    pub notation: String,          // standard notation such as `11-15` or `11x18x25`
+    pub path: Vec<(usize, usize)>, // row and column of every square visited, starting with the origin
+    pub captures: Vec<(usize, usize)>,
+    pub promotes: bool,
+    #[serde(skip)]
+    played: Move,
+}
+
+impl MoveView {
+    pub fn init(mv: &Move) -> MoveView {
+        let separator = if mv.is_capture() { "x" } else { "-" };
+        let squares: Vec<String> = mv
+            .path()
+            .into_iter()
+            .filter_map(Square::init_from_cord)
+            .map(|square| square.to_string())
+            .collect();
+        return MoveView {
+            notation: squares.join(separator),
+            path: mv.path(),
+            captures: mv.captures.clone(),
+            promotes: mv.promotes,
+            played: mv.clone(),
+        };
+    }
+
+    /// Squares the move visits as "[<x> <y> <x> <y> ...]"
+    fn get_path_text(&self) -> String {
+        let cords: Vec<String> = self
+            .path
+            .iter()
+            .map(|(x, y)| format!("{} {}", x, y))
+            .collect();
+        return format!("[{}]", cords.join(" "));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PieceView {
+    pub square: u32,
+    pub row: usize,
+    pub col: usize,
+    pub side: Side,
+    pub king: bool,
+}
+
+/// Time left on the clocks of a timed game, in milliseconds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClockView {
+    pub dark: u64,
+    pub light: u64,
+    pub running: Option<Side>, // side whose clock is running, None once the game is over
+}
+
+impl ClockView {
+    pub fn init(clock: &Clock) -> ClockView {
+        let now = Instant::now();
+        return ClockView {
+            dark: clock.get_remaining(Side::Dark, now).as_millis() as u64,
+            light: clock.get_remaining(Side::Light, now).as_millis() as u64,
+            running: clock.get_running(),
+        };
+    }
+}
+
+/// Position of a game along with whose turn it is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BoardView {
+    pub turn: Side,
+    pub player: String, // name of the player whose turn it is
+    pub fen: String,
+    pub pieces: Vec<PieceView>,
+    pub clock: Option<ClockView>, // None for an untimed game
+    #[serde(skip)]
+    text: String,
+}
+
+impl BoardView {
+    pub fn init(checkers: &mut Checkers) -> BoardView {
+        let turn = checkers.get_turn();
+        let fen = checkers.get_fen();
+        let board = *checkers.get_board();
+        let pieces = [Side::Dark, Side::Light]
+            .into_iter()
+            .flat_map(|side| board.get_player_pieces(side))
+            .filter_map(|piece| {
+                let square = Square::init_from_cord(piece.loc)?;
+                Some(PieceView {
+                    square: square.get_number(),
+                    row: piece.loc.0,
+                    col: piece.loc.1,
+                    side: piece.owner,
+                    king: piece.kinged,
+                })
+            })
+            .collect();
+        return BoardView {
+            turn,
+            player: checkers.get_player(turn).unwrap_or(turn.to_string()),
+            fen,
+            pieces,
+            clock: checkers.get_clock().map(ClockView::init),
+            text: board.get_board_as_string(),
+        };
+    }
+}
+
+/// How a finished game ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameOverView {
+    pub winner: Option<Side>,
+    pub winner_name: Option<String>,
+    pub draw_reason: Option<String>,
+    pub on_time: bool, // the loser ran out of time
+    pub description: String,
+}
+
+impl GameOverView {
+    pub fn init(checkers: &Checkers) -> GameOverView {
+        let result = checkers.get_result();
+        let (winner, winner_name, draw_reason, description) = match result {
+            GameResult::Win(side) | GameResult::WinOnTime(side) => {
+                let name = checkers.get_player(side).unwrap_or_default();
+                let mut description = format!("{} ({}) is the winner", name, side);
+                if let GameResult::WinOnTime(_) = result {
+                    description.push_str(" on time");
+                }
+                (Some(side), Some(name), None, description)
+            }
+            GameResult::Draw(reason) => (None, None, Some(reason.to_string()), result.to_string()),
+            GameResult::Ongoing => (None, None, None, result.to_string()),
+        };
+        return GameOverView {
+            winner,
+            winner_name,
+            draw_reason,
+            on_time: matches!(result, GameResult::WinOnTime(_)),
+            description,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DivideView {
+    #[serde(rename = "move")]
+    pub first_move: MoveView,
+    pub nodes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchView {
+    pub best_move: MoveView,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub pv: Vec<MoveView>,
+}
+
+impl SearchView {
+    pub fn init(result: &SearchResult) -> SearchView {
+        return SearchView {
+            best_move: MoveView::init(&result.best_move),
+            score: result.score,
+            depth: result.depth,
+            nodes: result.nodes,
+            pv: result.pv.iter().map(MoveView::init).collect(),
+        };
+    }
+}
+
+/// A game in the lobby
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameSummary {
+    pub id: u32,
+    pub dark: Option<String>, // name of the player playing dark, if one has joined
+    pub light: Option<String>,
+    pub status: String,               // waiting, playing or completed
+    pub time_control: Option<String>, // such as "fischer 300000 5000", None for an untimed game
+}
+
+impl GameSummary {
+    /// Summary as "<id> <dark name|-> <light name|-> <status> [<time control>]"
+    fn get_text(&self) -> String {
+        let dark = self.dark.as_deref().unwrap_or("-");
+        let light = self.light.as_deref().unwrap_or("-");
+        let text = format!("{} {} {} {}", self.id, dark, light, self.status);
+        match &self.time_control {
+            Some(time_control) => return format!("{} {}", text, time_control),
+            None => return text,
+        }
+    }
+}
+
+/// Contents of a response or event, serialised as the payload of a JSON message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Payload {
+    Welcome {
+        message: String,
+        version: u32,
+    },
+    Help {
+        commands: Vec<String>,
+    },
+    Board(BoardView),
+    GameStarted {
+        dark: String,
+        light: String,
+    },
+    GameOver(GameOverView),
+    Games {
+        games: Vec<GameSummary>,
+    },
+    Watching(GameSummary),
+    Joined {
+        game: u32,
+        name: String,
+        side: Side,
+        token: String, // reconnects to the game as the player
+    },
+    LegalMoves {
+        moves: Vec<MoveView>,
+    },
+    MovePlayed(MoveView),
+    Undone(MoveView),
+    Redone(MoveView),
+    History {
+        moves: Vec<MoveView>,
+    },
+    Square {
+        number: u32,
+        row: usize,
+        col: usize,
+        algebraic: String,
+    },
+    Fen {
+        fen: String,
+    },
+    PositionLoaded {
+        fen: String,
+    },
+    Pdn {
+        pdn: String,
+    },
+    GameLoaded {
+        moves: usize,
+    },
+    Offered {
+        side: Side,    // side of the player offering the change
+        offer: String, // command that makes the change once the opponent sends it too
+    },
+    OptionSet {
+        name: String,
+        value: usize,
+    },
+    Perft {
+        depth: u32,
+        nodes: u64,
+    },
+    Divide {
+        depth: u32,
+        moves: Vec<DivideView>,
+        total: u64,
+    },
+    Evaluation {
+        score: i32,
+    },
+    Counting {
+        command: String, // perft or divide, which sends its count as an event once it ends
+        depth: u32,
+    },
+    Searching,
+    BestMove(SearchView),
+    Stopping,
+}
+
+impl Payload {
+    /// Name of the payload when it is sent as an event
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Payload::Welcome { .. } => "welcome",
+            Payload::Help { .. } => "help",
+            Payload::Board(_) => "board",
+            Payload::GameStarted { .. } => "game_started",
+            Payload::GameOver(_) => "game_over",
+            Payload::Games { .. } => "games",
+            Payload::Watching(_) => "watching",
+            Payload::Joined { .. } => "joined",
+            Payload::LegalMoves { .. } => "legal_moves",
+            Payload::MovePlayed(_) => "move_played",
+            Payload::Undone(_) => "undone",
+            Payload::Redone(_) => "redone",
+            Payload::History { .. } => "history",
+            Payload::Square { .. } => "square",
+            Payload::Fen { .. } => "fen",
+            Payload::PositionLoaded { .. } => "position_loaded",
+            Payload::Pdn { .. } => "pdn",
+            Payload::GameLoaded { .. } => "game_loaded",
+            Payload::Offered { .. } => "offered",
+            Payload::OptionSet { .. } => "option_set",
+            Payload::Perft { .. } => "perft",
+            Payload::Divide { .. } => "divide",
+            Payload::Evaluation { .. } => "evaluation",
+            Payload::Counting { .. } => "counting",
+            Payload::Searching => "searching",
+            Payload::BestMove(_) => "best_move",
+            Payload::Stopping => "stopping",
+        }
+    }
+
+    /// Messages of the text protocol carrying the payload
+    pub fn to_text(&self) -> Vec<String> {
+        let text = match self {
+            Payload::Welcome { message, .. } => message.clone(),
+            Payload::Help { commands } => commands.join("\n"),
+            Payload::Board(board) => {
+                let mut lines = vec![
+                    format!("turn:{}", board.player),
+                    format!("board:{}", board.text),
+                ];
+                if let Some(clock) = &board.clock {
+                    lines.push(format!("clock:dark={} light={}", clock.dark, clock.light));
+                }
+                return lines;
+            }
+            Payload::GameStarted { .. } => String::from("Game Started"),
+            Payload::GameOver(view) => format!("status: game completed, {}!", view.description),
+            Payload::Games { games } => {
+                let games: Vec<String> = games.iter().map(GameSummary::get_text).collect();
+                format!("games:[{}]", games.join(", "))
+            }
+            Payload::Watching(game) => format!("watching:{}", game.get_text()),
+            Payload::Joined {
+                game,
+                name,
+                side,
+                token,
+            } => {
+                return vec![
+                    format!("player:{}", name),
+                    format!("side:{}", side),
+                    format!("game:{}", game),
+                    format!("token:{}", token),
+                ];
+            }
+            Payload::LegalMoves { moves } => {
+                let moves: Vec<String> = moves.iter().map(MoveView::get_path_text).collect();
+                format!("legal_moves:[{}]", moves.join(", "))
+            }
+            Payload::MovePlayed(view) => {
+                let (x, y) = view.played.destination();
+                format!("move_piece:\"successfully moved piece to ({}, {})\"", x, y)
+            }
+            Payload::Undone(view) => format!("undo:{}", view.played),
+            Payload::Redone(view) => format!("redo:{}", view.played),
+            Payload::History { moves } => {
+                let moves: Vec<String> = moves.iter().map(|view| view.played.to_string()).collect();
+                format!("history:[{}]", moves.join(", "))
+            }
+            Payload::Square {
+                number,
+                row,
+                col,
+                algebraic,
+            } => format!("square:{} ({}, {}) {}", number, row, col, algebraic),
+            Payload::Fen { fen } => format!("fen:{}", fen),
+            Payload::PositionLoaded { fen } => format!("load_fen:{}", fen),
+            Payload::Pdn { pdn } => format!("pdn:{}", pdn),
+            Payload::GameLoaded { moves } => format!("load_pdn:\"loaded {} moves\"", moves),
+            Payload::Offered { side, offer } => format!("offered:{} {}", side, offer),
+            Payload::OptionSet { name, value } => format!("set_option:{}={}", name, value),
+            Payload::Perft { nodes, .. } => format!("perft:{}", nodes),
+            Payload::Divide { moves, total, .. } => {
+                let counts: Vec<String> = moves
+                    .iter()
+                    .map(|entry| format!("{} {}", entry.first_move.get_path_text(), entry.nodes))
+                    .collect();
+                format!("divide:[{}] total:{}", counts.join(", "), total)
+            }
+            Payload::Evaluation { score } => format!("evaluation:{}", score),
+            Payload::Counting { command, .. } => format!("{}:\"counting\"", command),
+            Payload::Searching => String::from("best_move:\"searching\""),
+            Payload::BestMove(search) => {
+                let pv: Vec<String> = search.pv.iter().map(MoveView::get_path_text).collect();
+                format!(
+                    "best_move:{} score:{} depth:{} nodes:{} pv:[{}]",
+                    search.best_move.get_path_text(),
+                    search.score,
+                    search.depth,
+                    search.nodes,
+                    pv.join(", ")
+                )
+            }
+            Payload::Stopping => String::from("stop:\"stopping search\""),
+        };
+        return vec![text];
+    }
+}
+
+/// Something sent to a client, either the response to its request or an event such as the
+/// board changing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    Response(Result<Payload, ProtocolError>),
+    Event(Payload),
+}
@@ -0,0 +1,718 @@
+mod game;
+mod lobby;
+
+use crate::engine::{self, Engine, Position, SearchLimits, StopSignal, MAX_HASH_MB, MAX_THREADS};
+use crate::game::{Side, TimeControl};
+use crate::protocol::{
+    self, Command, DivideView, Encoding, ErrorCode, MoveView, Payload, ProtocolError, Reply,
+    Request, SearchView, PROTOCOL_VERSION,
+};
+use game::Game;
+use lobby::Lobby;
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{sleep, spawn, JoinHandle};
+use std::time::{Duration, Instant};
+use tungstenite::accept_hdr;
+use tungstenite::handshake::server::{Request as HandshakeRequest, Response};
+use tungstenite::protocol::Message;
+use tungstenite::WebSocket;
+
+/// Path clients connect to for the JSON protocol, any other path speaks the text protocol
+pub const JSON_PATH: &str = "/json";
+
+/// How long to wait for a client message before sending any events from its game
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often the clocks of every game are checked for a player running out of time
+const CLOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Deepest perft or divide a client can run, deeper counts tie up a core for minutes
+const MAX_PERFT_DEPTH: u32 = 10;
+
+const HELP: [&str; 27] = [
+    "\"donger\" -- Status check msg. Outputs \"turn:<name>\\nboard:<game_board>\".",
+    "\"list_games\" -- List every game on the server. Outputs \"games:[<id> <dark name|-> <light name|-> <waiting|playing|completed>, ...]\".",
+    "\"create_game <name> [fischer <base ms> <increment ms> | bronstein <base ms> <delay ms> | per_move <ms>]\" -- Create a new game and join it as dark, which moves first. A time control gives the game clocks that start once both players have joined, a player whose time runs out loses. The board is followed by \"clock:dark=<ms> light=<ms>\" in timed games. Outputs \"player:<name>\nside:dark\ngame:<id>\ntoken:<session token>\".",
+    "\"join_game <id> <name>\" -- Join a game waiting for a player, the second player to join plays light. Outputs \"player:<name>\nside:<dark|light>\ngame:<id>\ntoken:<session token>\".",
+    "\"reconnect <token>\" -- Take back your side of a game after losing your connection, using the session token given when you joined. Outputs \"player:<name>\nside:<dark|light>\ngame:<id>\ntoken:<session token>\" followed by the board.",
+    "\"watch <id>\" -- Follow a game without playing in it, every change to the game is sent as it happens. Outputs \"watching:<id> <dark name|-> <light name|-> <status>\" followed by the board.",
+    "\"set_player_name <name>\" -- Join the oldest game waiting for a player, or create one if there are none. Outputs \"player:<name>\nside:<dark|light>\ngame:<id>\ntoken:<session token>\".",
+    "\"move_piece <target x> <target y> <destination x> <destination y> [<destination x> <destination y> ...]\" -- Attempt to move a piece from a target location to a destination location, listing every landing of a multi-jump in order. Squares can also be numbered 1-32 like \"move_piece 11-15\", \"move_piece 11 15\" or \"move_piece 11x18x25\", or given in algebraic notation like \"move_piece c3 d4\". Outputs \"move_piece: success..\".",
+    "\"legal_moves\" -- List every complete move available to the player whose turn it is. Outputs \"legal_moves:[[<x> <y> ...], ...]\".",
+    "\"undo\" -- Ask to take back your last move, the opponent agrees by sending \"undo\" too. Until then the take back is offered to them with \"offered:<side> undo\". Outputs \"undo:<move>\" followed by the board.",
+    "\"redo\" -- Ask to make your move that was taken back again, the opponent agrees by sending \"redo\" too. Until then it is offered to them with \"offered:<side> redo\". Outputs \"redo:<move>\" followed by the board.",
+    "\"history\" -- List every move made this game. Outputs \"history:[<move>, ...]\".",
+    "\"square <square>\" -- Convert a square between its 1-32 number, row and column, and algebraic notation, given in any of them. Outputs \"square:<number> (<x>, <y>) <algebraic>\".",
+    "\"fen\" -- Describe the current position as a FEN string. Outputs \"fen:<W|B>:W<square>,K<square>,...:B<square>,...\".",
+    "\"load_fen <fen>\" -- Restart the game from a position such as \"W:W21,22,K30:B1,2,K9\", squares are numbered 1-32. A position loaded while waiting for the opponent is where the game starts from. Once the game has started the opponent has to send the same command to agree, until then the position is offered to them with \"offered:<side> load_fen <fen>\". Outputs \"load_fen:<fen>\" followed by the board.",
+    "\"export_pdn\" -- Export the game as Portable Draughts Notation. Outputs \"pdn:<game record>\".",
+    "\"load_pdn <game record>\" -- Start a new game from Portable Draughts Notation, replaying its moves from the position in its FEN tag if it has one. The game is unchanged if the record fails to load. A game loaded while waiting for the opponent carries on once they join. Once the game has started the opponent has to send the same record to agree, until then it is offered to them with \"offered:<side> load_pdn <game record>\". Outputs \"load_pdn:<status>\" followed by the board.",
+    "\"set_option <name> <value>\" -- Configure the engine, \"hash\" sets the transposition table size in MB (1 to 256), \"quiescence\" the most capture plies searched past the search depth and \"threads\" the number of search threads (1 to 16). Outputs \"set_option:<name>=<value>\".",
+    "\"perft <depth>\" -- Count the move sequences of the given length from the current position, up to a depth of 10. The count runs while other commands are carried out, one at a time for each client. Outputs \"perft:\"counting\"\" at once and \"perft:<count>\" once the count ends.",
+    "\"divide <depth>\" -- Split the perft count by the first move, up to a depth of 10, running like perft. Outputs \"divide:\"counting\"\" at once and \"divide:[[<x> <y> ...] <count>, ...] total:<count>\" once the count ends.",
+    "\"evaluate\" -- Score the board with the engine's weights, positive scores favour dark and negative scores favour light. Outputs \"evaluation:<score>\".",
+    "\"best_move [<depth>] [depth <plies>] [nodes <count>] [movetime <ms>] [time <ms>] [inc <ms>]\" -- Search for the best move for the player whose turn it is, within a fixed depth, node count or move time or using time left on the clock plus increment. The search runs while other commands are carried out, one at a time for each client. Outputs \"best_move:\"searching\"\" at once and \"best_move:[<x> <y> ...] score:<score> depth:<depth> nodes:<nodes> pv:[[<x> <y> ...], ...]\" once the search ends.",
+    "\"stop\" -- Stop your running search, it replies with the best move found so far. Outputs \"stop:<status>\".",
+    "",
+    "Clients connecting to /json speak JSON instead, every request is an object like {\"version\": 1, \"id\": 7, \"command\": \"move_piece\", \"payload\": {\"path\": [11, 15]}} naming a command above, with \"donger\" called \"status\" and arguments given as payload fields.",
+    "Each request gets a response {\"version\": 1, \"id\": 7, \"command\": \"move_piece\", \"status\": \"ok\", \"payload\": {...}}, or \"status\": \"error\" with an \"error\": {\"code\": <code>, \"message\": <message>} instead of the payload.",
+    "Changes to the game are sent to everyone in it as events {\"version\": 1, \"event\": \"board\", \"payload\": {...}}, text clients are sent the same messages as the player making the change.",
+];
+
+/// State shared by every connection to the server
+pub struct Server {
+    lobby: Mutex<Lobby>,
+    next_connection_id: AtomicU64,
+}
+
+/// State of a single client connection
+pub struct Connection {
+    id: u64,
+    game: Option<Arc<Mutex<Game>>>, // game the client has joined
+    side: Option<Side>,             // side the client plays in its game
+    engine: Arc<Mutex<Engine>>,     // held by the client's search while it runs
+    stop_signal: StopSignal,        // stops the client's own search
+    search: Option<JoinHandle<()>>, // search thread, which sends its result as an event
+    count: Option<JoinHandle<()>>,  // perft or divide thread, which sends its count as an event
+    event_sender: Sender<Reply>,    // given to games the client subscribes to
+    events: Receiver<Reply>,        // events of the client's game caused by other clients
+}
+
+impl Connection {
+    pub fn init(server: &Server) -> Connection {
+        let (event_sender, events) = channel();
+        let stop_signal = StopSignal::init();
+        return Connection {
+            id: server.next_connection_id.fetch_add(1, Ordering::Relaxed),
+            game: None,
+            side: None,
+            engine: Arc::new(Mutex::new(Engine::init_with_stop_signal(
+                stop_signal.clone(),
+            ))),
+            stop_signal,
+            search: None,
+            count: None,
+            event_sender,
+            events,
+        };
+    }
+
+    /// Events other clients have caused in the client's game, and the results of the client's
+    /// searches, since they were last taken
+    pub fn take_events(&self) -> Vec<Reply> {
+        return self.events.try_iter().collect();
+    }
+
+    pub fn is_searching(&self) -> bool {
+        return self
+            .search
+            .as_ref()
+            .is_some_and(|search| !search.is_finished());
+    }
+
+    pub fn is_counting(&self) -> bool {
+        return self
+            .count
+            .as_ref()
+            .is_some_and(|count| !count.is_finished());
+    }
+
+    /// Run a perft or divide count on a thread of its own, so deep counts don't hold up the
+    /// client's other commands and events. The payload `count` returns is sent as an event.
+    fn start_count(&mut self, count: impl FnOnce() -> Payload + Send + 'static) {
+        let events = self.event_sender.clone();
+        self.count = Some(spawn(move || {
+            // the client may have gone, leaving nobody to send the count to
+            let _ = events.send(Reply::Event(count()));
+        }));
+    }
+
+    /// Lock the engine. A search that panicked can only have left hints in the transposition
+    /// table, so the engine is still used.
+    fn get_engine(&self) -> MutexGuard<'_, Engine> {
+        return self.engine.lock().unwrap_or_else(PoisonError::into_inner);
+    }
+
+    /// Search for the best move on a thread of its own, so the client can still send commands
+    /// such as `stop` while it runs. The result is sent as a `best_move` event.
+    fn start_search(&mut self, position: Position, limits: SearchLimits) {
+        // cleared before the thread starts, so a stop sent straight after isn't lost
+        self.stop_signal.reset();
+        let engine = Arc::clone(&self.engine);
+        let events = self.event_sender.clone();
+        self.search = Some(spawn(move || {
+            let result = engine
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .best_move(&position, &limits);
+            if let Some(result) = result {
+                // the client may have gone, leaving nobody to send the result to
+                let _ = events.send(Reply::Event(Payload::BestMove(SearchView::init(&result))));
+            }
+        }));
+    }
+}
+
+/// A client that goes away stops its search rather than leaving it running
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.stop_signal.stop();
+    }
+}
+
+impl Server {
+    pub fn init() -> Server {
+        return Server {
+            lobby: Mutex::new(Lobby::init()),
+            next_connection_id: AtomicU64::new(0),
+        };
+    }
+
+    /// Listen for websocket clients, serving each one on its own thread
+    pub fn run(self: Arc<Self>, address: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(address)?;
+        let server = Arc::clone(&self);
+        spawn(move || loop {
+            sleep(CLOCK_POLL_INTERVAL);
+            server.check_clocks();
+            server.expire_sessions(Instant::now());
+        });
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let server = Arc::clone(&self);
+                    spawn(move || server.serve(stream));
+                }
+                Err(e) => println!("failed to accept connection, {}", e),
+            }
+        }
+        return Ok(());
+    }
+
+    /// Serve a websocket client until it disconnects
+    // the handshake callback's error type is set by tungstenite
+    #[allow(clippy::result_large_err)]
+    pub fn serve(&self, stream: TcpStream) {
+        let mut encoding = Encoding::Text;
+        let handshake = accept_hdr(stream, |request: &HandshakeRequest, response: Response| {
+            if request.uri().path() == JSON_PATH {
+                encoding = Encoding::Json;
+            }
+            Ok(response)
+        });
+        let mut websocket = match handshake {
+            Ok(websocket) => websocket,
+            Err(e) => {
+                println!("websocket handshake failed, {}", e);
+                return;
+            }
+        };
+        let mut connection = Connection::init(self);
+        match self.serve_websocket(&mut websocket, encoding, &mut connection) {
+            Ok(()) | Err(tungstenite::Error::ConnectionClosed) => println!("connection closed"),
+            Err(e) => println!("connection dropped, {}", e),
+        }
+    }
+
+    // tungstenite's own error type
+    #[allow(clippy::result_large_err)]
+    fn serve_websocket(
+        &self,
+        websocket: &mut WebSocket<TcpStream>,
+        encoding: Encoding,
+        connection: &mut Connection,
+    ) -> tungstenite::Result<()> {
+        // Send greeting message to player client
+        let welcome = Reply::Event(Payload::Welcome {
+            message: String::from("Welcome! Send \"help\" to see all available commands."),
+            version: PROTOCOL_VERSION,
+        });
+        for text in protocol::encode(encoding, &welcome, "", &None) {
+            websocket.write_message(Message::Text(text))?;
+        }
+
+        // reads time out so events from other players are sent while the client is idle
+        websocket
+            .get_mut()
+            .set_read_timeout(Some(EVENT_POLL_INTERVAL))?;
+        // Server thread starts listening for websocket messages
+        loop {
+            for event in connection.take_events() {
+                for text in protocol::encode(encoding, &event, "", &None) {
+                    websocket.write_message(Message::Text(text))?;
+                }
+            }
+            let msg_string = match websocket.read_message() {
+                Ok(Message::Text(text)) => text,
+                // tungstenite answers the close, the following read ends the connection
+                Ok(Message::Close(_)) => continue,
+                Ok(_) => {
+                    println!("Received non-text websocket message, ignoring it");
+                    continue;
+                }
+                Err(tungstenite::Error::Io(e))
+                    if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e),
+            };
+            let request = Request::decode(encoding, &msg_string);
+            let replies = match &request.command {
+                Ok(command) => {
+                    // a bug in a command fails that command rather than the whole connection,
+                    // games it had locked are restored or left unusable
+                    let handled = panic::catch_unwind(AssertUnwindSafe(|| {
+                        self.handle_command(connection, command)
+                    }));
+                    handled.unwrap_or_else(|_| {
+                        respond(Err(ProtocolError::init(
+                            ErrorCode::ServerError,
+                            "the server failed to carry out the command",
+                        )))
+                    })
+                }
+                Err(error) => {
+                    println!(
+                        "default: received invalid request={}, {}",
+                        msg_string.trim(),
+                        error
+                    );
+                    vec![Reply::Response(Err(error.clone()))]
+                }
+            };
+            for reply in &replies {
+                for text in request.encode(encoding, reply) {
+                    websocket.write_message(Message::Text(text))?;
+                }
+            }
+        }
+    }
+
+    /// Carry out a command for a client. Games are only locked while a command is carried
+    /// out on them, searches run on a copy of the position.
+    ///
+    /// **Returns** - the response to the command along with any events it caused, in the order
+    /// they are sent
+    pub fn handle_command(&self, connection: &mut Connection, command: &Command) -> Vec<Reply> {
+        let result = match command {
+            Command::Help => Ok(respond(Ok(Payload::Help {
+                commands: HELP.iter().map(|line| line.to_string()).collect(),
+            }))),
+            Command::ListGames => Ok(self.list_games()),
+            Command::CreateGame { name, time_control } => {
+                let time_control = time_control.map(|time_control| time_control.get_time_control());
+                self.join_game(connection, JoinTarget::New(time_control), name)
+            }
+            Command::JoinGame { game, name } => {
+                self.join_game(connection, JoinTarget::Game(*game), name)
+            }
+            Command::SetPlayerName { name } => self.join_game(connection, JoinTarget::Open, name),
+            Command::Watch { game } => self.watch_game(connection, *game),
+            Command::Reconnect { token } => self.reconnect(connection, token),
+            Command::Status => with_game(connection, |game| game.status()),
+            Command::LegalMoves => with_game(connection, |game| game.legal_moves()),
+            Command::MovePiece { path } => {
+                update_game(connection, |game, side| game.move_piece(side, path))
+            }
+            Command::Undo => update_game(connection, |game, side| game.undo(side)),
+            Command::Redo => update_game(connection, |game, side| game.redo(side)),
+            Command::History => with_game(connection, |game| game.history()),
+            Command::Square { square } => Ok(respond(match square.get_square() {
+                Some(square) => {
+                    let (row, col) = square.get_cord();
+                    Ok(Payload::Square {
+                        number: square.get_number(),
+                        row,
+                        col,
+                        algebraic: square.get_algebraic(),
+                    })
+                }
+                None => Err(ProtocolError::init(
+                    ErrorCode::InvalidArgument,
+                    "not a playable square",
+                )),
+            })),
+            Command::Fen => with_game(connection, |game| game.fen()),
+            Command::LoadFen { fen } => {
+                update_game(connection, |game, side| game.load_fen(side, fen))
+            }
+            Command::ExportPdn => with_game(connection, |game| game.export_pdn()),
+            Command::LoadPdn { pdn } => {
+                update_game(connection, |game, side| game.load_pdn(side, pdn))
+            }
+            Command::SetOption { name, value } => {
+                if connection.is_searching() {
+                    Err(ProtocolError::init(
+                        ErrorCode::InvalidRequest,
+                        "options can't be changed while a search is running, stop it first",
+                    ))
+                } else {
+                    Ok(respond(set_option(
+                        &mut connection.get_engine(),
+                        name,
+                        *value,
+                    )))
+                }
+            }
+            Command::Perft { depth } => {
+                let depth = *depth;
+                check_can_count(connection, depth)
+                    .and_then(|()| get_position(connection))
+                    .map(|position| {
+                        connection.start_count(move || Payload::Perft {
+                            depth,
+                            nodes: engine::perft(&position, depth),
+                        });
+                        respond(Ok(Payload::Counting {
+                            command: String::from("perft"),
+                            depth,
+                        }))
+                    })
+            }
+            Command::Divide { depth } => {
+                let depth = *depth;
+                check_can_count(connection, depth)
+                    .and_then(|()| get_position(connection))
+                    .map(|position| {
+                        connection.start_count(move || {
+                            let divided = engine::divide(&position, depth);
+                            Payload::Divide {
+                                depth,
+                                total: divided.iter().map(|(_, count)| count).sum(),
+                                moves: divided
+                                    .iter()
+                                    .map(|(mv, nodes)| DivideView {
+                                        first_move: MoveView::init(mv),
+                                        nodes: *nodes,
+                                    })
+                                    .collect(),
+                            }
+                        });
+                        respond(Ok(Payload::Counting {
+                            command: String::from("divide"),
+                            depth,
+                        }))
+                    })
+            }
+            // the engine isn't locked, so the board can be scored while a search runs
+            Command::Evaluate => get_position(connection).map(|position| {
+                // scored for the side to move, then given from dark's point of view
+                let score = engine::evaluate(&position.board, position.turn);
+                respond(Ok(Payload::Evaluation {
+                    score: match position.turn {
+                        Side::Dark => score,
+                        Side::Light => -score,
+                    },
+                }))
+            }),
+            Command::BestMove(request) => {
+                if connection.is_searching() {
+                    Err(ProtocolError::init(
+                        ErrorCode::InvalidRequest,
+                        "a search is already running, stop it first",
+                    ))
+                } else {
+                    get_position(connection).and_then(|position| {
+                        if position.legal_moves().is_empty() {
+                            return Err(ProtocolError::init(
+                                ErrorCode::NoMoves,
+                                "no moves available",
+                            ));
+                        }
+                        let limits = request.clone().unwrap_or_default().get_limits();
+                        connection.start_search(position, limits);
+                        Ok(respond(Ok(Payload::Searching)))
+                    })
+                }
+            }
+            Command::Stop => {
+                connection.stop_signal.stop();
+                Ok(respond(Ok(Payload::Stopping)))
+            }
+        };
+        return result.unwrap_or_else(|error| respond(Err(error)));
+    }
+
+    /// End every game where the player to move has run out of time, even if nobody is
+    /// sending commands to it
+    pub fn check_clocks(&self) {
+        let games = lock(&self.lobby).get_games();
+        for game in games {
+            if let Ok(mut game) = lock_game(&game) {
+                game.check_time();
+            }
+        }
+    }
+
+    /// Forget sessions and games that are over or were abandoned as of `now`, see
+    /// `Lobby::expire`
+    pub fn expire_sessions(&self, now: Instant) {
+        lock(&self.lobby).expire(now);
+    }
+
+    fn list_games(&self) -> Vec<Reply> {
+        let games = lock(&self.lobby).get_games();
+        return respond(Ok(Payload::Games {
+            games: games
+                .iter()
+                .filter_map(|game| Some(lock_game(game).ok()?.get_summary()))
+                .collect(),
+        }));
+    }
+
+    /// Add the client to a game as a player, starting the game once both players have joined
+    fn join_game(
+        &self,
+        connection: &mut Connection,
+        target: JoinTarget,
+        name: &str,
+    ) -> Result<Vec<Reply>, ProtocolError> {
+        println!("join_game: name={}", name);
+        self.check_not_in_game(connection)?;
+        // the lobby stays locked until the player has joined, so an open game can't fill up
+        // in between
+        let mut lobby = lock(&self.lobby);
+        let token = lobby.new_token()?;
+        let game = match target {
+            JoinTarget::Game(id) => lobby.get_game(id).ok_or_else(|| {
+                ProtocolError::init(ErrorCode::NoSuchGame, format!("no game with id {}", id))
+            })?,
+            JoinTarget::Open => {
+                let open = lobby
+                    .get_games()
+                    .into_iter()
+                    .find(|game| lock_game(game).is_ok_and(|game| game.is_open()));
+                open.unwrap_or_else(|| lobby.create_game(None))
+            }
+            JoinTarget::New(time_control) => lobby.create_game(time_control),
+        };
+        let mut locked = lock_game(&game)?;
+        locked.touch(Instant::now());
+        let (side, mut replies) = locked.join(name, connection.id)?;
+        locked.subscribe(connection.id, connection.event_sender.clone());
+        locked.publish(&replies, Some(connection.id));
+        let id = locked.get_id();
+        replies.push(Reply::Response(Ok(Payload::Joined {
+            game: id,
+            name: name.to_string(),
+            side,
+            token: token.clone(),
+        })));
+        lobby.create_session(token, id, side);
+        drop(locked);
+        connection.game = Some(game);
+        connection.side = Some(side);
+        return Ok(replies);
+    }
+
+    /// Take back the seat of a player with the token it was given on joining, such as after
+    /// the connection dropped. The game carries on where it was left.
+    fn reconnect(
+        &self,
+        connection: &mut Connection,
+        token: &str,
+    ) -> Result<Vec<Reply>, ProtocolError> {
+        self.check_not_in_game(connection)?;
+        let (game, side) = lock(&self.lobby).get_session(token).ok_or_else(|| {
+            ProtocolError::init(ErrorCode::NoSuchSession, "no session with that token")
+        })?;
+        let mut locked = lock_game(&game)?;
+        locked.touch(Instant::now());
+        let replies = locked.reconnect(side, token, connection.id, connection.event_sender.clone());
+        drop(locked);
+        connection.game = Some(game);
+        connection.side = Some(side);
+        return Ok(replies);
+    }
+
+    /// Send the client the events of a game without it playing in the game
+    fn watch_game(
+        &self,
+        connection: &mut Connection,
+        id: u32,
+    ) -> Result<Vec<Reply>, ProtocolError> {
+        self.check_not_in_game(connection)?;
+        let game = lock(&self.lobby).get_game(id).ok_or_else(|| {
+            ProtocolError::init(ErrorCode::NoSuchGame, format!("no game with id {}", id))
+        })?;
+        let replies = lock_game(&game)?.watch(connection.id, connection.event_sender.clone());
+        connection.game = Some(game);
+        return Ok(replies);
+    }
+
+    /// Clients can only be in one game at a time, whether playing or watching. A game that has
+    /// finished, was removed from the lobby or was left unusable by a server error doesn't
+    /// count, the client leaves it so it can move on to another.
+    fn check_not_in_game(&self, connection: &mut Connection) -> Result<(), ProtocolError> {
+        let Some(game) = &connection.game else {
+            return Ok(());
+        };
+        let playing = {
+            let lobby = lock(&self.lobby);
+            match lock_game(game) {
+                Ok(game) if !game.is_completed() && lobby.get_game(game.get_id()).is_some() => {
+                    Some(game.get_id())
+                }
+                Ok(mut game) => {
+                    game.unsubscribe(connection.id);
+                    None
+                }
+                Err(_) => None,
+            }
+        };
+        let Some(id) = playing else {
+            connection.game = None;
+            connection.side = None;
+            return Ok(());
+        };
+        let message = match connection.side {
+            Some(_) => format!("already playing in game {}", id),
+            None => format!("already watching game {}", id),
+        };
+        return Err(ProtocolError::init(ErrorCode::AlreadyPlaying, message));
+    }
+}
+
+/// Game a client asks to join
+enum JoinTarget {
+    Game(u32),
+    Open, // the oldest game waiting for a player, or a new untimed one if there are none
+    New(Option<TimeControl>),
+}
+
+/// Lock the lobby. It is only ever changed by single inserts, so a panic while it was held
+/// can't have left it half changed and the lock is still used rather than poisoned for every
+/// other client.
+fn lock(lobby: &Mutex<Lobby>) -> MutexGuard<'_, Lobby> {
+    return lobby.lock().unwrap_or_else(PoisonError::into_inner);
+}
+
+/// Lock a game. Commands put the game back as it was when they panic (see `with_game`), so a
+/// poisoned game was left half changed by some other panic and is never played again.
+///
+/// **Returns** - an error if the game is unusable
+fn lock_game(game: &Mutex<Game>) -> Result<MutexGuard<'_, Game>, ProtocolError> {
+    return game.lock().map_err(|_| {
+        ProtocolError::init(
+            ErrorCode::ServerError,
+            "the game is unusable after a server error",
+        )
+    });
+}
+
+fn respond(response: Result<Payload, ProtocolError>) -> Vec<Reply> {
+    return vec![Reply::Response(response)];
+}
+
+/// Carry out a command on the client's game, locking it for the duration. If the command
+/// panics the game is put back as it was before the command, rather than left half changed.
+fn with_game<R>(
+    connection: &Connection,
+    command: impl FnOnce(&mut Game) -> R,
+) -> Result<R, ProtocolError> {
+    let Some(game) = &connection.game else {
+        return Err(ProtocolError::init(
+            ErrorCode::NotPlaying,
+            "join, create or watch a game first",
+        ));
+    };
+    let mut game = lock_game(game)?;
+    game.touch(Instant::now());
+    let saved = game.clone();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        game.check_time();
+        command(&mut game)
+    }));
+    let Ok(result) = result else {
+        *game = saved;
+        return Err(ProtocolError::init(
+            ErrorCode::ServerError,
+            "the server failed to carry out the command, the game is unchanged",
+        ));
+    };
+    game.print_board();
+    return Ok(result);
+}
+
+/// Carry out a command that changes the client's game, sending the events it causes to
+/// everyone else in the game. Only players can change the game, not watchers.
+fn update_game(
+    connection: &Connection,
+    command: impl FnOnce(&mut Game, Side) -> Vec<Reply>,
+) -> Result<Vec<Reply>, ProtocolError> {
+    let Some(side) = connection.side else {
+        let message = match connection.game {
+            Some(_) => "watchers can't change the game",
+            None => "join or create a game first",
+        };
+        return Err(ProtocolError::init(ErrorCode::NotPlaying, message));
+    };
+    return with_game(connection, |game| {
+        if !game.is_controlled_by(side, connection.id) {
+            return Err(ProtocolError::init(
+                ErrorCode::NotPlaying,
+                "another connection has taken over your side",
+            ));
+        }
+        let replies = command(game, side);
+        game.publish(&replies, Some(connection.id));
+        Ok(replies)
+    })?;
+}
+
+/// Copy of the position in the client's game, for the engine to work on without holding the
+/// game
+fn get_position(connection: &Connection) -> Result<Position, ProtocolError> {
+    return with_game(connection, Game::get_position)?
+        .ok_or_else(|| ProtocolError::init(ErrorCode::NoMoves, "no moves available"));
+}
+
+/// Check that a perft or divide of `depth` can be started, one count runs at a time for each
+/// client
+fn check_can_count(connection: &Connection, depth: u32) -> Result<(), ProtocolError> {
+    if depth > MAX_PERFT_DEPTH {
+        return Err(ProtocolError::init(
+            ErrorCode::InvalidArgument,
+            format!("depth can be at most {}", MAX_PERFT_DEPTH),
+        ));
+    }
+    if connection.is_counting() {
+        return Err(ProtocolError::init(
+            ErrorCode::InvalidRequest,
+            "a count is already running, wait for it to end",
+        ));
+    }
+    return Ok(());
+}
+
+fn set_option(engine: &mut Engine, name: &str, value: usize) -> Result<Payload, ProtocolError> {
+    let out_of_range = |maximum: usize| {
+        ProtocolError::init(
+            ErrorCode::InvalidArgument,
+            format!("{} must be from 1 to {}", name, maximum),
+        )
+    };
+    match name {
+        "hash" if (1..=MAX_HASH_MB).contains(&value) => engine.set_hash_size(value),
+        "hash" => return Err(out_of_range(MAX_HASH_MB)),
+        "quiescence" => engine.options.quiescence_depth = value as u32,
+        "threads" if (1..=MAX_THREADS).contains(&value) => engine.options.threads = value,
+        "threads" => return Err(out_of_range(MAX_THREADS)),
+        _ => {
+            return Err(ProtocolError::init(
+                ErrorCode::InvalidArgument,
+                format!("invalid option or value for {}", name),
+            ))
+        }
+    }
+    return Ok(Payload::OptionSet {
+        name: name.to_string(),
+        value,
+    });
+}
@@ -0,0 +1,521 @@
+use crate::engine::Position;
+use crate::game::{Checkers, PdnGame, Side, TimeControl};
+use crate::protocol::{
+    path_to_move, BoardView, ErrorCode, GameOverView, GameSummary, MoveView, Payload,
+    ProtocolError, Reply, SquareInput,
+};
+use std::fmt;
+use std::sync::mpsc::Sender;
+use std::time::Instant;
+
+/// A connection that is sent the events of a game
+#[derive(Clone)]
+struct Subscriber {
+    connection: u64, // id of the connection
+    events: Sender<Reply>,
+}
+
+/// A player in a game
+#[derive(Clone)]
+struct Player {
+    name: String,
+    connection: u64, // id of the connection controlling the player's side
+}
+
+/// Change to a game in progress that is only made once both players have asked for it
+#[derive(Clone, PartialEq, Eq)]
+enum Offer {
+    Undo,
+    Redo,
+    LoadFen(String),
+    LoadPdn(String),
+}
+
+/// Command making the change, as the opponent would send it to agree
+impl fmt::Display for Offer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Offer::Undo => write!(f, "undo"),
+            Offer::Redo => write!(f, "redo"),
+            Offer::LoadFen(fen) => write!(f, "load_fen {}", fen),
+            Offer::LoadPdn(pdn) => write!(f, "load_pdn {}", pdn),
+        }
+    }
+}
+
+/// A game on the server along with the players that have joined it
+#[derive(Clone)]
+pub struct Game {
+    id: u32,
+    checkers: Checkers,
+    players: Vec<Player>, // players that have joined, dark first
+    subscribers: Vec<Subscriber>,
+    offer: Option<(Side, Offer)>, // change a player has offered, waiting for the opponent
+    loaded: bool,                 // a position or game was loaded before both players joined
+    last_active: Instant,         // when a client last used the game
+}
+
+impl Game {
+    pub fn init(id: u32, time_control: Option<TimeControl>) -> Game {
+        let mut checkers = Checkers::init();
+        if let Some(time_control) = time_control {
+            checkers.set_time_control(time_control);
+        }
+        return Game {
+            id,
+            checkers,
+            players: vec![],
+            subscribers: vec![],
+            offer: None,
+            loaded: false,
+            last_active: Instant::now(),
+        };
+    }
+
+    pub fn get_id(&self) -> u32 {
+        return self.id;
+    }
+
+    pub fn is_completed(&self) -> bool {
+        return self.checkers.is_completed();
+    }
+
+    /// Record that a client used the game at `now`
+    pub fn touch(&mut self, now: Instant) {
+        self.last_active = now;
+    }
+
+    pub fn get_last_active(&self) -> Instant {
+        return self.last_active;
+    }
+
+    /// Check if the game is still waiting for a player to join
+    pub fn is_open(&self) -> bool {
+        return self.players.len() < 2 && !self.checkers.is_completed();
+    }
+
+    pub fn get_summary(&self) -> GameSummary {
+        let status = if self.checkers.is_completed() {
+            "completed"
+        } else if self.players.len() < 2 {
+            "waiting"
+        } else {
+            "playing"
+        };
+        return GameSummary {
+            id: self.id,
+            dark: self.players.first().map(|player| player.name.clone()),
+            light: self.players.get(1).map(|player| player.name.clone()),
+            status: status.to_string(),
+            time_control: self
+                .checkers
+                .get_clock()
+                .map(|clock| clock.get_time_control().to_string()),
+        };
+    }
+
+    /// Position to search or evaluate, the board is copied so the game isn't held while the
+    /// engine runs
+    ///
+    /// **Returns** - None if the game has finished
+    pub fn get_position(&mut self) -> Option<Position> {
+        if self.checkers.is_completed() {
+            return None;
+        }
+        return Some(Position::init(
+            *self.checkers.get_board(),
+            self.checkers.get_turn(),
+        ));
+    }
+
+    /// Check if a connection controls a side, only the last connection to join or reconnect
+    /// as a player controls its side
+    pub fn is_controlled_by(&self, side: Side, connection: u64) -> bool {
+        return self
+            .players
+            .get(get_player_index(side))
+            .is_some_and(|player| player.connection == connection);
+    }
+
+    /// Send the events of the game to a connection from now on
+    pub fn subscribe(&mut self, connection: u64, events: Sender<Reply>) {
+        self.subscribers.push(Subscriber { connection, events });
+    }
+
+    /// Stop sending the events of the game to a connection that has left it
+    pub fn unsubscribe(&mut self, connection: u64) {
+        self.subscribers
+            .retain(|subscriber| subscriber.connection != connection);
+    }
+
+    /// Hand control of a side to a new connection, the connection previously controlling it
+    /// is no longer sent the events of the game
+    ///
+    /// **Returns** - the `joined` response followed by the result of the game, if it has
+    /// finished, and the board
+    pub fn reconnect(
+        &mut self,
+        side: Side,
+        token: &str,
+        connection: u64,
+        events: Sender<Reply>,
+    ) -> Vec<Reply> {
+        let Some(player) = self.players.get_mut(get_player_index(side)) else {
+            return vec![Reply::Response(Err(ProtocolError::init(
+                ErrorCode::ServerError,
+                "session has no player",
+            )))];
+        };
+        let previous = player.connection;
+        player.connection = connection;
+        let name = player.name.clone();
+        self.unsubscribe(previous);
+        self.subscribe(connection, events);
+        return self.respond_with_board(Ok(Payload::Joined {
+            game: self.id,
+            name,
+            side,
+            token: token.to_string(),
+        }));
+    }
+
+    /// Send the events of the game to a connection that isn't playing in it
+    ///
+    /// **Returns** - the summary of the game followed by its result, if it has finished, and
+    /// the board
+    pub fn watch(&mut self, connection: u64, events: Sender<Reply>) -> Vec<Reply> {
+        self.subscribe(connection, events);
+        return self.respond_with_board(Ok(Payload::Watching(self.get_summary())));
+    }
+
+    /// Send the replies to a change of the game as events to every subscriber except the
+    /// connection that made the change, which already has them. The response becomes an event
+    /// too, such as the move that was played. Nothing is sent for a failed change. Subscribers
+    /// that have disconnected are dropped.
+    pub fn publish(&mut self, replies: &[Reply], from: Option<u64>) {
+        let mut events = vec![];
+        for reply in replies {
+            match reply {
+                Reply::Response(Ok(payload)) | Reply::Event(payload) => {
+                    events.push(Reply::Event(payload.clone()))
+                }
+                Reply::Response(Err(_)) => return,
+            }
+        }
+        self.subscribers.retain(|subscriber| {
+            Some(subscriber.connection) == from
+                || events
+                    .iter()
+                    .all(|event| subscriber.events.send(event.clone()).is_ok())
+        });
+    }
+
+    /// End the game if the player to move has run out of time, sending its result to everyone
+    /// in the game
+    pub fn check_time(&mut self) {
+        if self.checkers.check_time() {
+            let events: Vec<Reply> = self
+                .game_over_event()
+                .into_iter()
+                .chain([self.board_event()])
+                .collect();
+            self.publish(&events, None);
+        }
+    }
+
+    pub fn print_board(&self) {
+        self.checkers.print_board();
+    }
+
+    /// Event announcing the result of the game, if it has finished
+    fn game_over_event(&self) -> Option<Reply> {
+        if !self.checkers.is_completed() {
+            return None;
+        }
+        return Some(Reply::Event(Payload::GameOver(GameOverView::init(
+            &self.checkers,
+        ))));
+    }
+
+    fn board_event(&mut self) -> Reply {
+        return Reply::Event(Payload::Board(BoardView::init(&mut self.checkers)));
+    }
+
+    /// Check that both players agree to a change of the game. The first player to ask for the
+    /// change offers it to their opponent, and it is made once the opponent asks for the same
+    /// change. Any other change or move withdraws the offer. Before the opponent has joined a
+    /// position or game can be loaded without them, and is where the game starts from.
+    ///
+    /// **Returns** - the reply to the player if the change can't be made yet, None if it can
+    fn agree(&mut self, side: Side, offer: Offer) -> Option<Vec<Reply>> {
+        if self.players.len() < 2 {
+            if let Offer::LoadFen(_) | Offer::LoadPdn(_) = offer {
+                self.loaded = true;
+                return None;
+            }
+            return Some(vec![Reply::Response(Err(ProtocolError::init(
+                ErrorCode::NotPlaying,
+                "the game starts once both players have joined",
+            )))]);
+        }
+        if self.offer.take() == Some((side.opponent(), offer.clone())) {
+            return None;
+        }
+        let response = Payload::Offered {
+            side,
+            offer: offer.to_string(),
+        };
+        self.offer = Some((side, offer));
+        return Some(vec![Reply::Response(Ok(response))]);
+    }
+
+    /// Respond to a command that changed the game, following the response with the result of
+    /// the game if it has finished and then the board
+    fn respond_with_board(&mut self, response: Result<Payload, ProtocolError>) -> Vec<Reply> {
+        let mut replies = vec![Reply::Response(response)];
+        replies.extend(self.game_over_event());
+        replies.push(self.board_event());
+        return replies;
+    }
+
+    /// Add a player to the game, starting it once both players have joined
+    ///
+    /// **Returns** - the side the player plays along with the events of the game starting
+    pub fn join(
+        &mut self,
+        name: &str,
+        connection: u64,
+    ) -> Result<(Side, Vec<Reply>), ProtocolError> {
+        if self.players.len() >= 2 {
+            return Err(ProtocolError::init(
+                ErrorCode::GameFull,
+                "already max players registered",
+            ));
+        }
+        if self.checkers.is_completed() {
+            return Err(ProtocolError::init(
+                ErrorCode::GameCompleted,
+                "the game is already completed",
+            ));
+        }
+
+        // Add player name to list of gamers
+        self.players.push(Player {
+            name: name.to_string(),
+            connection,
+        });
+        let side = if self.players.len() == 1 {
+            Side::Dark
+        } else {
+            Side::Light
+        };
+        let mut events = vec![];
+        // Start game if we have two players registered!
+        if let [dark, light] = self.players.as_slice() {
+            let (dark, light) = (&dark.name, &light.name);
+            events.push(Reply::Event(Payload::GameStarted {
+                dark: dark.clone(),
+                light: light.clone(),
+            }));
+            if self.loaded {
+                self.checkers
+                    .setup_players_keeping_position(dark.clone(), light.clone());
+            } else if self
+                .checkers
+                .setup_players(dark.clone(), light.clone())
+                .is_none()
+            {
+                println!("Failed to initialize checkers board.");
+                return Err(ProtocolError::init(
+                    ErrorCode::ServerError,
+                    "checker board failed to setup",
+                ));
+            }
+            events.push(self.board_event());
+        }
+        return Ok((side, events));
+    }
+
+    /// The board is the response, sent after the result of a completed game
+    pub fn status(&mut self) -> Vec<Reply> {
+        let mut replies: Vec<Reply> = self.game_over_event().into_iter().collect();
+        replies.push(Reply::Response(Ok(Payload::Board(BoardView::init(
+            &mut self.checkers,
+        )))));
+        return replies;
+    }
+
+    pub fn legal_moves(&self) -> Vec<Reply> {
+        return vec![Reply::Response(Ok(Payload::LegalMoves {
+            moves: self
+                .checkers
+                .legal_moves()
+                .iter()
+                .map(MoveView::init)
+                .collect(),
+        }))];
+    }
+
+    pub fn move_piece(&mut self, side: Side, path: &[SquareInput]) -> Vec<Reply> {
+        let Some(mv) = path_to_move(path) else {
+            return vec![Reply::Response(Err(ProtocolError::init(
+                ErrorCode::InvalidArgument,
+                "failed to parse move, expected row and column pairs, square numbers or algebraic squares",
+            )))];
+        };
+        let response = match self.checkers.move_piece(side, &mv) {
+            Ok(outcome) => {
+                self.offer = None;
+                Ok(Payload::MovePlayed(MoveView::init(&outcome.played)))
+            }
+            Err(e) => {
+                let (d_x, d_y) = mv.destination();
+                let context = format!("failed to move piece to ({}, {})", d_x, d_y);
+                Err(ProtocolError::init_from_move_error(&e, &context))
+            }
+        };
+        return self.respond_with_board(response);
+    }
+
+    /// Take back the last move, once the player who made it has asked to and their opponent
+    /// agrees
+    pub fn undo(&mut self, side: Side) -> Vec<Reply> {
+        // the player to move can't take back the opponent's move, only agree to its take back
+        let mover = self.checkers.get_turn().opponent();
+        let available = !self.checkers.get_move_history().is_empty();
+        if let Err(error) = self.check_can_change(side, mover, Offer::Undo, available) {
+            return vec![Reply::Response(Err(error))];
+        }
+        if let Some(replies) = self.agree(side, Offer::Undo) {
+            return replies;
+        }
+        let response = match self.checkers.undo_move() {
+            Some(mv) => Ok(Payload::Undone(MoveView::init(&mv))),
+            None => Err(ProtocolError::init(
+                ErrorCode::NoMoves,
+                "no moves to take back",
+            )),
+        };
+        return self.respond_with_board(response);
+    }
+
+    /// Make the last move taken back again, once the player whose move it is has asked to and
+    /// their opponent agrees
+    pub fn redo(&mut self, side: Side) -> Vec<Reply> {
+        let mover = self.checkers.get_turn();
+        let available = self.checkers.can_redo();
+        if let Err(error) = self.check_can_change(side, mover, Offer::Redo, available) {
+            return vec![Reply::Response(Err(error))];
+        }
+        if let Some(replies) = self.agree(side, Offer::Redo) {
+            return replies;
+        }
+        let response = match self.checkers.redo_move() {
+            Some(mv) => Ok(Payload::Redone(MoveView::init(&mv))),
+            None => Err(ProtocolError::init(ErrorCode::NoMoves, "no moves to redo")),
+        };
+        return self.respond_with_board(response);
+    }
+
+    /// Check that `side` can ask for a move of `mover` to be taken back or made again, if there
+    /// is one `available`. Only the player of the move can ask, their opponent can only agree
+    /// to it, and a finished game can't be changed.
+    fn check_can_change(
+        &self,
+        side: Side,
+        mover: Side,
+        offer: Offer,
+        available: bool,
+    ) -> Result<(), ProtocolError> {
+        if self.checkers.is_completed() {
+            return Err(ProtocolError::init(
+                ErrorCode::GameCompleted,
+                "the game is already completed",
+            ));
+        }
+        if !available {
+            let message = match offer {
+                Offer::Undo => "no moves to take back",
+                _ => "no moves to redo",
+            };
+            return Err(ProtocolError::init(ErrorCode::NoMoves, message));
+        }
+        if side != mover && self.offer != Some((mover, offer)) {
+            return Err(ProtocolError::init(
+                ErrorCode::NotYourTurn,
+                "only the player of the move can ask for it, the opponent can agree once asked",
+            ));
+        }
+        return Ok(());
+    }
+
+    pub fn history(&self) -> Vec<Reply> {
+        return vec![Reply::Response(Ok(Payload::History {
+            moves: self
+                .checkers
+                .get_move_history()
+                .iter()
+                .map(MoveView::init)
+                .collect(),
+        }))];
+    }
+
+    pub fn fen(&self) -> Vec<Reply> {
+        return vec![Reply::Response(Ok(Payload::Fen {
+            fen: self.checkers.get_fen(),
+        }))];
+    }
+
+    /// Restart the game from a position, once both players have asked for it or before the
+    /// opponent has joined
+    pub fn load_fen(&mut self, side: Side, fen: &str) -> Vec<Reply> {
+        // the position is loaded into a copy first, so an invalid one is never offered
+        let mut loaded = self.checkers.clone();
+        if let Err(e) = loaded.load_fen(fen) {
+            return self.respond_with_board(Err(ProtocolError::init(
+                ErrorCode::InvalidArgument,
+                format!("failed to load position, {}", e),
+            )));
+        }
+        if let Some(replies) = self.agree(side, Offer::LoadFen(fen.to_string())) {
+            return replies;
+        }
+        self.checkers = loaded;
+        return self.respond_with_board(Ok(Payload::PositionLoaded {
+            fen: self.checkers.get_fen(),
+        }));
+    }
+
+    pub fn export_pdn(&self) -> Vec<Reply> {
+        return vec![Reply::Response(Ok(Payload::Pdn {
+            pdn: self.checkers.to_pdn().to_string(),
+        }))];
+    }
+
+    /// Start a new game from a PDN record, once both players have asked for it or before the
+    /// opponent has joined
+    pub fn load_pdn(&mut self, side: Side, pdn: &str) -> Vec<Reply> {
+        let mut loaded = self.checkers.clone();
+        if let Err(e) = PdnGame::parse(pdn).and_then(|pdn| loaded.load_pdn(&pdn)) {
+            return self.respond_with_board(Err(ProtocolError::init(
+                ErrorCode::InvalidArgument,
+                format!("failed to load game, {}", e),
+            )));
+        }
+        if let Some(replies) = self.agree(side, Offer::LoadPdn(pdn.to_string())) {
+            return replies;
+        }
+        self.checkers = loaded;
+        return self.respond_with_board(Ok(Payload::GameLoaded {
+            moves: self.checkers.get_move_history().len(),
+        }));
+    }
+}
+
+/// Index into the players of the player playing a side
+fn get_player_index(side: Side) -> usize {
+    match side {
+        Side::Dark => 0,
+        Side::Light => 1,
+    }
+}
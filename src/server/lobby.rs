@@ -0,0 +1,118 @@
+use super::game::Game;
+use crate::game::{Side, TimeControl};
+use crate::protocol::{ErrorCode, ProtocolError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a game that hasn't finished is kept, along with its sessions, after a client last
+/// used it
+const SESSION_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long a finished game stays listed, so its result can still be seen
+const FINISHED_GAME_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// A player's seat in a game, which a client can take back with the session's token
+struct Session {
+    game: u32,
+    side: Side,
+}
+
+/// Every game on the server by id. Each game has its own lock so games are played
+/// independently, the lobby is only locked to find a game or add one.
+pub struct Lobby {
+    games: BTreeMap<u32, Arc<Mutex<Game>>>,
+    next_id: u32,
+    sessions: HashMap<String, Session>, // by token
+    finished: HashMap<u32, Instant>,    // when each finished game was first seen finished
+}
+
+impl Lobby {
+    pub fn init() -> Lobby {
+        return Lobby {
+            games: BTreeMap::new(),
+            next_id: 1,
+            sessions: HashMap::new(),
+            finished: HashMap::new(),
+        };
+    }
+
+    pub fn create_game(&mut self, time_control: Option<TimeControl>) -> Arc<Mutex<Game>> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let game = Arc::new(Mutex::new(Game::init(id, time_control)));
+        self.games.insert(id, Arc::clone(&game));
+        return game;
+    }
+
+    pub fn get_game(&self, id: u32) -> Option<Arc<Mutex<Game>>> {
+        return self.games.get(&id).cloned();
+    }
+
+    /// Every game, oldest first
+    pub fn get_games(&self) -> Vec<Arc<Mutex<Game>>> {
+        return self.games.values().cloned().collect();
+    }
+
+    /// Random 128 bit token as hex that no session has, from the operating system's secure
+    /// random number generator
+    pub fn new_token(&self) -> Result<String, ProtocolError> {
+        loop {
+            let mut bytes = [0; 16];
+            getrandom::getrandom(&mut bytes).map_err(|e| {
+                ProtocolError::init(
+                    ErrorCode::ServerError,
+                    format!("failed to create a session token, {}", e),
+                )
+            })?;
+            let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            if !self.sessions.contains_key(&token) {
+                return Ok(token);
+            }
+        }
+    }
+
+    /// Start a session for the player of a side in a game, taken back with a token from
+    /// `new_token`
+    pub fn create_session(&mut self, token: String, game: u32, side: Side) {
+        self.sessions.insert(token, Session { game, side });
+    }
+
+    /// Game and side of the session with the given token
+    pub fn get_session(&self, token: &str) -> Option<(Arc<Mutex<Game>>, Side)> {
+        let session = self.sessions.get(token)?;
+        return Some((self.get_game(session.game)?, session.side));
+    }
+
+    /// Drop sessions whose game has finished or hasn't been used for `SESSION_TIMEOUT` at
+    /// `now`. Games go too once they finished more than `FINISHED_GAME_TIMEOUT` ago, or when
+    /// they haven't finished but nobody has used them for `SESSION_TIMEOUT`. A game left
+    /// unusable by a server error counts as finished, and a finished game that is started
+    /// again, such as by loading a position, no longer does.
+    pub fn expire(&mut self, now: Instant) {
+        let mut inactive = HashSet::new();
+        for (id, game) in &self.games {
+            let Ok(game) = game.lock() else {
+                self.finished.entry(*id).or_insert(now);
+                continue;
+            };
+            if game.is_completed() {
+                self.finished.entry(*id).or_insert(now);
+                continue;
+            }
+            self.finished.remove(id);
+            if now.saturating_duration_since(game.get_last_active()) >= SESSION_TIMEOUT {
+                inactive.insert(*id);
+            }
+        }
+        let finished = &self.finished;
+        self.sessions.retain(|_, session| {
+            !finished.contains_key(&session.game) && !inactive.contains(&session.game)
+        });
+        self.games.retain(|id, _| match finished.get(id) {
+            Some(since) => now.saturating_duration_since(*since) < FINISHED_GAME_TIMEOUT,
+            None => !inactive.contains(id),
+        });
+        let games = &self.games;
+        self.finished.retain(|id, _| games.contains_key(id));
+    }
+}